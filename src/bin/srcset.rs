@@ -0,0 +1,398 @@
+//! `srcset`: parse, validate, select from and format `srcset` attributes.
+//!
+//! Built with the `cli` feature. The `srcset` is read from the arguments after the
+//! subcommand's options or, when there are none, from standard input.
+
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use srcset_parse::picture::{Environment, Img, Picture};
+use srcset_parse::{parse, try_parse, ImageCandidate, ParseError, SelectionStrategy};
+
+const USAGE: &str = "\
+usage: srcset <command> [options] [srcset]
+
+Reads the srcset from the arguments, or from standard input if there are none.
+
+commands:
+  parse [--json]        list the candidates
+  validate              report every problem, exiting with status 1 if there is one
+  select [options]      show the candidate a browser would fetch
+      --viewport <px>       viewport width, in CSS pixels (default: 1024)
+      --viewport-height <px>
+                            viewport height, in CSS pixels (default: 768)
+      --dpr <ratio>         device pixel ratio (default: 1)
+      --sizes <sizes>       the sizes attribute
+      --layout-width <px>   the width a lazy-loaded image is laid out at, which
+                            sizes=auto resolves to
+      --browser <name>      the browser to model: spec, chromium, firefox or
+                            webkit (default: spec)
+      --explain             explain each step of the selection
+  format                rewrite a valid srcset in canonical form
+";
+
+/// A usage error, reported with exit status 2.
+#[derive(Debug, PartialEq)]
+struct UsageError(String);
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Parse {
+        json: bool,
+    },
+    Validate,
+    Select {
+        viewport_width: f64,
+        viewport_height: f64,
+        device_pixel_ratio: f64,
+        sizes: Option<String>,
+        layout_width: Option<f64>,
+        strategy: SelectionStrategy,
+        explain: bool,
+    },
+    Format,
+}
+
+/// Parses the arguments after the program name, returning the command and the
+/// `srcset` given as arguments, if any.
+fn parse_args(args: &[String]) -> Result<(Command, Option<String>), UsageError> {
+    let (name, mut rest) = match args.split_first() {
+        Some((name, rest)) => (name.as_str(), rest),
+        None => return Err(UsageError("missing command".to_string())),
+    };
+
+    let mut json = false;
+    let mut viewport_width = 1024.0;
+    let mut viewport_height = 768.0;
+    let mut device_pixel_ratio = 1.0;
+    let mut sizes = None;
+    let mut layout_width = None;
+    let mut strategy = SelectionStrategy::Spec;
+    let mut explain = false;
+
+    while let Some((option, tail)) = rest.split_first() {
+        if option == "--" {
+            rest = tail;
+            break;
+        }
+        if !option.starts_with("--") {
+            break;
+        }
+
+        let mut value = || -> Result<&String, UsageError> {
+            let (value, tail) = tail
+                .split_first()
+                .ok_or_else(|| UsageError(format!("{option} requires a value")))?;
+            rest = tail;
+            Ok(value)
+        };
+        let number = |value: &String| -> Result<f64, UsageError> {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n > 0.0)
+                .ok_or_else(|| UsageError(format!("{option} must be a positive number")))
+        };
+
+        match (name, option.as_str()) {
+            ("parse", "--json") => {
+                json = true;
+                rest = tail;
+            }
+            ("select", "--explain") => {
+                explain = true;
+                rest = tail;
+            }
+            ("select", "--viewport") => viewport_width = number(value()?)?,
+            ("select", "--viewport-height") => viewport_height = number(value()?)?,
+            ("select", "--dpr") => device_pixel_ratio = number(value()?)?,
+            ("select", "--sizes") => sizes = Some(value()?.clone()),
+            ("select", "--layout-width") => layout_width = Some(number(value()?)?),
+            ("select", "--browser") => {
+                strategy = match value()?.to_ascii_lowercase().as_str() {
+                    "spec" => SelectionStrategy::Spec,
+                    "chromium" | "chrome" | "edge" => SelectionStrategy::Chromium,
+                    "firefox" => SelectionStrategy::Firefox,
+                    "webkit" | "safari" => SelectionStrategy::WebKit,
+                    other => return Err(UsageError(format!("unknown browser {other}"))),
+                }
+            }
+            _ => return Err(UsageError(format!("unknown option {option} for {name}"))),
+        }
+    }
+
+    let command = match name {
+        "parse" => Command::Parse { json },
+        "validate" => Command::Validate,
+        "select" => Command::Select {
+            viewport_width,
+            viewport_height,
+            device_pixel_ratio,
+            sizes,
+            layout_width,
+            strategy,
+            explain,
+        },
+        "format" => Command::Format,
+        _ => return Err(UsageError(format!("unknown command {name}"))),
+    };
+    let srcset = (!rest.is_empty()).then(|| rest.join(" "));
+    Ok((command, srcset))
+}
+
+/// Formats an optional descriptor value for the table, with `-` for `None`.
+fn cell(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+/// Lays out the candidates as a table with a header row.
+fn table(candidates: &[ImageCandidate]) -> String {
+    let mut rows = vec![["url", "width", "height", "density"].map(String::from)];
+    rows.extend(candidates.iter().map(|c| {
+        [
+            c.url.clone(),
+            cell(c.width),
+            cell(c.height),
+            cell(c.density),
+        ]
+    }));
+
+    let url_width = rows.iter().map(|row| row[0].chars().count()).max();
+    let url_width = url_width.unwrap_or_default();
+    rows.iter()
+        .map(|[url, width, height, density]| {
+            let line = format!("{url:<url_width$}  {width:>7}  {height:>7}  {density:>7}");
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+/// Describes each problem in `error`, one per line.
+fn report(error: &ParseError) -> String {
+    error
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| format!("error: {diagnostic}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs `command`, returning what to print to standard output.
+fn run(command: Command, srcset: &str) -> Result<String, String> {
+    match command {
+        Command::Parse { json: false } => Ok(table(&parse(srcset))),
+        Command::Parse { json: true } => serde_json::to_string_pretty(&parse(srcset))
+            .map(|json| json + "\n")
+            .map_err(|error| error.to_string()),
+        Command::Validate => match try_parse(srcset) {
+            Ok(srcset) => Ok(format!("valid: {} candidates\n", srcset.len())),
+            Err(error) => Err(report(&error)),
+        },
+        Command::Select {
+            viewport_width,
+            viewport_height,
+            device_pixel_ratio,
+            sizes,
+            layout_width,
+            strategy,
+            explain,
+        } => {
+            let mut img = Img::default().with_srcset(srcset);
+            if let Some(sizes) = &sizes {
+                img = img.with_sizes(sizes);
+            }
+            if let Some(layout_width) = layout_width {
+                img = img.with_lazy_loading().with_layout_width(layout_width);
+            }
+            let env = Environment::new(viewport_width, viewport_height, device_pixel_ratio)
+                .with_strategy(strategy);
+            let picture = Picture::new(img);
+            let (selection, trace) = picture.select_with_trace(&env);
+            match selection {
+                Some(_) if explain => Ok(format!("{trace}\n")),
+                Some(selection) => Ok(format!("{}\n", selection.candidate)),
+                None if explain => Err(trace.to_string()),
+                None => Err("no candidate can be selected".to_string()),
+            }
+        }
+        Command::Format => try_parse(srcset)
+            .map_err(|error| report(&error))?
+            .to_srcset_string()
+            .map(|formatted| formatted + "\n")
+            .map_err(|error| error.to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.first().map(String::as_str), Some("-h" | "--help")) {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let (command, srcset) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(UsageError(message)) => {
+            eprint!("srcset: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let srcset = match srcset {
+        Some(srcset) => srcset,
+        None => {
+            let mut input = String::new();
+            if let Err(error) = io::stdin().read_to_string(&mut input) {
+                eprintln!("srcset: can't read standard input: {error}");
+                return ExitCode::from(2);
+            }
+            input
+        }
+    };
+
+    match run(command, srcset.trim()) {
+        Ok(output) => {
+            print!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse_args, run, Command, UsageError};
+    use srcset_parse::SelectionStrategy;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_options_and_the_srcset() {
+        assert_eq!(
+            parse_args(&args(&[
+                "select",
+                "--dpr",
+                "3",
+                "--sizes",
+                "50vw",
+                "--browser",
+                "Chrome",
+                "a.png 1x,",
+                "b.png 400w"
+            ])),
+            Ok((
+                Command::Select {
+                    viewport_width: 1024.0,
+                    viewport_height: 768.0,
+                    device_pixel_ratio: 3.0,
+                    sizes: Some("50vw".to_string()),
+                    layout_width: None,
+                    strategy: SelectionStrategy::Chromium,
+                    explain: false,
+                },
+                Some("a.png 1x, b.png 400w".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_args(&args(&["parse", "--json"])),
+            Ok((Command::Parse { json: true }, None))
+        );
+        assert_eq!(
+            parse_args(&args(&["format", "--", "--weird.png"])),
+            Ok((Command::Format, Some("--weird.png".to_string())))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse_args(&[]).is_err());
+        assert!(parse_args(&args(&["frobnicate"])).is_err());
+        assert_eq!(
+            parse_args(&args(&["select", "--dpr"])),
+            Err(UsageError("--dpr requires a value".to_string()))
+        );
+        assert_eq!(
+            parse_args(&args(&["select", "--viewport", "wide"])),
+            Err(UsageError(
+                "--viewport must be a positive number".to_string()
+            ))
+        );
+        assert!(parse_args(&args(&["format", "--json"])).is_err());
+    }
+
+    #[test]
+    fn runs_commands() {
+        assert_eq!(
+            run(
+                Command::Parse { json: false },
+                "a.png 1x, long-name.png 100w"
+            )
+            .unwrap(),
+            "url              width   height  density\n\
+             a.png                -        -        1\n\
+             long-name.png      100        -        -\n"
+        );
+        assert_eq!(
+            run(Command::Validate, "a.png 1x, b.png 1x 2x").unwrap_err(),
+            "error: conflicting descriptor `2x` (at 19..21)"
+        );
+        assert_eq!(
+            run(Command::Format, "a.png   1.0x ,b.png 2x").unwrap(),
+            "a.png 1x, b.png 2x\n"
+        );
+        assert_eq!(
+            run(Command::Format, "a.png 1x, b.png 1x 2x").unwrap_err(),
+            "error: conflicting descriptor `2x` (at 19..21)"
+        );
+        let select = Command::Select {
+            viewport_width: 375.0,
+            viewport_height: 812.0,
+            device_pixel_ratio: 3.0,
+            sizes: Some("(max-width: 400px) 50vw, 100vw".to_string()),
+            layout_width: None,
+            strategy: SelectionStrategy::Spec,
+            explain: false,
+        };
+        assert_eq!(
+            run(select, "s.png 400w, m.png 600w, l.png 1200w").unwrap(),
+            "m.png 600w\n"
+        );
+        let select = Command::Select {
+            viewport_width: 375.0,
+            viewport_height: 812.0,
+            device_pixel_ratio: 3.0,
+            sizes: Some("auto, 100vw".to_string()),
+            layout_width: Some(100.0),
+            strategy: SelectionStrategy::Spec,
+            explain: false,
+        };
+        assert_eq!(
+            run(select, "s.png 400w, m.png 600w, l.png 1200w").unwrap(),
+            "s.png 400w\n"
+        );
+        let select = Command::Select {
+            viewport_width: 375.0,
+            viewport_height: 812.0,
+            device_pixel_ratio: 2.0,
+            sizes: None,
+            layout_width: None,
+            strategy: SelectionStrategy::Spec,
+            explain: true,
+        };
+        assert_eq!(
+            run(select, "s.png 400w, m.png 750w").unwrap(),
+            "source size: 375px, the viewport width\n\
+             device pixel ratio: 2 (Spec strategy)\n\
+             candidates:\n  \
+             1. s.png 400w: 1.067x\n  \
+             2. m.png 750w: 2x, selected\n\
+             selected m.png: 2x is the lowest density covering 2x\n"
+        );
+    }
+}
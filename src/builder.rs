@@ -0,0 +1,316 @@
+//! Generating `srcset`s from width or density ladders.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{to_srcset_string, BuildError, ImageCandidate};
+
+/// How the URL of each candidate is generated.
+enum UrlSource {
+    Template(String),
+    Function(Box<dyn Fn(u32) -> String>),
+}
+
+/// Builds the candidates of a `srcset` from a list of widths or densities.
+///
+/// URLs come from a function of the pixel width to generate the image at, or from a
+/// template where `{width}` and `{density}` are replaced.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::SrcsetBuilder;
+///
+/// let srcset = SrcsetBuilder::new(|w| format!("/cat.jpg?w={w}"))
+///     .widths([320, 640, 1280])
+///     .to_srcset_string()
+///     .unwrap();
+/// assert_eq!(srcset, "/cat.jpg?w=320 320w, /cat.jpg?w=640 640w, /cat.jpg?w=1280 1280w");
+///
+/// let srcset = SrcsetBuilder::from_template("/cat@{density}x.jpg")
+///     .densities(400, [1.0, 1.5, 2.0])
+///     .to_srcset_string()
+///     .unwrap();
+/// assert_eq!(srcset, "/cat@1x.jpg 1x, /cat@1.5x.jpg 1.5x, /cat@2x.jpg 2x");
+/// ```
+pub struct SrcsetBuilder {
+    url: UrlSource,
+    widths: Vec<u32>,
+    base_width: u32,
+    densities: Vec<f64>,
+    /// A base width given after densities were added for a different one.
+    conflicting_base_width: Option<u32>,
+}
+
+impl fmt::Debug for SrcsetBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let url = match &self.url {
+            UrlSource::Template(template) => template.as_str(),
+            UrlSource::Function(_) => "<function>",
+        };
+        f.debug_struct("SrcsetBuilder")
+            .field("url", &url)
+            .field("widths", &self.widths)
+            .field("base_width", &self.base_width)
+            .field("densities", &self.densities)
+            .finish()
+    }
+}
+
+impl SrcsetBuilder {
+    /// Creates a builder generating URLs with `url`, which is given the pixel width
+    /// of each candidate.
+    pub fn new(url: impl Fn(u32) -> String + 'static) -> Self {
+        Self::with_url_source(UrlSource::Function(Box::new(url)))
+    }
+
+    /// Creates a builder generating URLs from `template`, replacing `{width}` with the
+    /// pixel width of each candidate and `{density}` with its density.
+    pub fn from_template(template: impl Into<String>) -> Self {
+        Self::with_url_source(UrlSource::Template(template.into()))
+    }
+
+    fn with_url_source(url: UrlSource) -> Self {
+        Self {
+            url,
+            widths: Vec::new(),
+            base_width: 0,
+            densities: Vec::new(),
+            conflicting_base_width: None,
+        }
+    }
+
+    /// Adds candidates with `w` descriptors.
+    pub fn widths(mut self, widths: impl IntoIterator<Item = u32>) -> Self {
+        self.widths.extend(widths);
+        self
+    }
+
+    /// Adds candidates with `x` descriptors, for an image displayed `base_width` CSS
+    /// pixels wide. A `2x` candidate is generated at twice that width.
+    ///
+    /// All densities must share one base width: calling this again with a
+    /// different one makes [`SrcsetBuilder::build`] fail.
+    pub fn densities(mut self, base_width: u32, densities: impl IntoIterator<Item = f64>) -> Self {
+        if self.densities.is_empty() {
+            self.base_width = base_width;
+        } else if base_width != self.base_width {
+            self.conflicting_base_width.get_or_insert(base_width);
+        }
+        self.densities.extend(densities);
+        self
+    }
+
+    fn url(&self, width: u32, density: Option<f64>) -> String {
+        match &self.url {
+            UrlSource::Template(template) => {
+                let url = template.replace("{width}", &width.to_string());
+                match density {
+                    Some(density) => url.replace("{density}", &density.to_string()),
+                    None => url,
+                }
+            }
+            UrlSource::Function(url) => url(width),
+        }
+    }
+
+    /// Validates the ladder and generates the candidates.
+    pub fn build(&self) -> Result<Vec<ImageCandidate>, BuildError> {
+        match (self.widths.is_empty(), self.densities.is_empty()) {
+            (true, true) => return Err(BuildError::Empty),
+            (false, false) => return Err(BuildError::MixedDescriptors),
+            _ => {}
+        }
+        if let Some(second) = self.conflicting_base_width {
+            return Err(BuildError::ConflictingBaseWidths {
+                first: self.base_width,
+                second,
+            });
+        }
+        if let UrlSource::Template(template) = &self.url {
+            if self.densities.is_empty() && template.contains("{density}") {
+                return Err(BuildError::DensityInWidthTemplate);
+            }
+        }
+
+        let values: Vec<f64> = if self.densities.is_empty() {
+            self.widths.iter().map(|&w| f64::from(w)).collect()
+        } else {
+            if self.base_width == 0 {
+                return Err(BuildError::NonPositive { value: 0.0 });
+            }
+            self.densities.clone()
+        };
+
+        for (i, &value) in values.iter().enumerate() {
+            // Also rejects NaN.
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(BuildError::NonPositive { value });
+            }
+            if values[..i].contains(&value) {
+                return Err(BuildError::Duplicate { value });
+            }
+        }
+
+        let candidates = if self.densities.is_empty() {
+            self.widths
+                .iter()
+                .map(|&width| ImageCandidate {
+                    url: self.url(width, None),
+                    width: Some(f64::from(width)),
+                    density: None,
+                    height: None,
+                })
+                .collect()
+        } else {
+            self.densities
+                .iter()
+                .map(|&density| {
+                    let width = round(f64::from(self.base_width) * density) as u32;
+                    ImageCandidate {
+                        url: self.url(width, Some(density)),
+                        width: None,
+                        density: Some(density),
+                        height: None,
+                    }
+                })
+                .collect()
+        };
+
+        Ok(candidates)
+    }
+
+    /// Validates the ladder and serializes the candidates into a `srcset` string.
+    pub fn to_srcset_string(&self) -> Result<String, BuildError> {
+        to_srcset_string(&self.build()?).map_err(BuildError::Serialize)
+    }
+}
+
+/// Rounds half away from zero, like [`f64::round`], which needs `std`.
+#[cfg(feature = "std")]
+fn round(value: f64) -> f64 {
+    value.round()
+}
+
+/// Rounds half away from zero, like [`f64::round`], which needs `std`.
+#[cfg(not(feature = "std"))]
+fn round(value: f64) -> f64 {
+    // Values this large are already whole, and `NaN` stays `NaN`.
+    const WHOLE: f64 = 4_503_599_627_370_496.0;
+    if !(-WHOLE < value && value < WHOLE) {
+        return value;
+    }
+    // Unlike adding 0.5 and truncating, this is exact: the difference between a
+    // value and its truncation is always representable.
+    let truncated = value as i64 as f64;
+    let fraction = value - truncated;
+    if fraction >= 0.5 {
+        truncated + 1.0
+    } else if fraction <= -0.5 {
+        truncated - 1.0
+    } else {
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{round, SrcsetBuilder};
+    use crate::{BuildError, SerializeError};
+
+    #[test]
+    fn generates_density_urls_from_the_pixel_width() {
+        let candidates = SrcsetBuilder::new(|w| format!("/img?w={w}"))
+            .densities(300, [1.0, 2.0, 2.5])
+            .build()
+            .unwrap();
+        let urls: Vec<_> = candidates.iter().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, vec!["/img?w=300", "/img?w=600", "/img?w=750"]);
+        assert_eq!(candidates[2].density, Some(2.5));
+    }
+
+    #[test]
+    fn rounds_half_away_from_zero() {
+        assert_eq!(round(0.49999999999999994), 0.0);
+        assert_eq!(round(0.5), 1.0);
+        assert_eq!(round(2.5), 3.0);
+        assert_eq!(round(-2.5), -3.0);
+        assert_eq!(round(-0.4), 0.0);
+        assert_eq!(round(1e300), 1e300);
+        assert!(round(f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn fills_in_templates() {
+        let srcset = SrcsetBuilder::from_template("/img/{width}.jpg")
+            .widths([100, 200])
+            .to_srcset_string()
+            .unwrap();
+        assert_eq!(srcset, "/img/100.jpg 100w, /img/200.jpg 200w");
+
+        let srcset = SrcsetBuilder::from_template("/img/{width}/{density}.jpg")
+            .densities(100, [1.0, 2.0])
+            .to_srcset_string()
+            .unwrap();
+        assert_eq!(srcset, "/img/100/1.jpg 1x, /img/200/2.jpg 2x");
+
+        assert_eq!(
+            SrcsetBuilder::from_template("/img/{width}/{density}.jpg")
+                .widths([100, 200])
+                .build(),
+            Err(BuildError::DensityInWidthTemplate)
+        );
+    }
+
+    #[test]
+    fn validates_the_ladder() {
+        let builder = || SrcsetBuilder::from_template("/img.jpg?w={width}");
+        assert_eq!(builder().build(), Err(BuildError::Empty));
+        assert_eq!(
+            builder().widths([100]).densities(100, [1.0]).build(),
+            Err(BuildError::MixedDescriptors)
+        );
+        assert_eq!(
+            builder().widths([100, 0]).build(),
+            Err(BuildError::NonPositive { value: 0.0 })
+        );
+        assert_eq!(
+            builder().densities(0, [1.0]).build(),
+            Err(BuildError::NonPositive { value: 0.0 })
+        );
+        assert!(matches!(
+            builder().densities(100, [1.0, f64::NAN]).build(),
+            Err(BuildError::NonPositive { value }) if value.is_nan()
+        ));
+        assert_eq!(
+            builder()
+                .densities(100, [1.0])
+                .densities(100, [2.0])
+                .build()
+                .map(|c| c.len()),
+            Ok(2)
+        );
+        assert_eq!(
+            builder()
+                .densities(100, [1.0])
+                .densities(200, [2.0])
+                .build(),
+            Err(BuildError::ConflictingBaseWidths {
+                first: 100,
+                second: 200
+            })
+        );
+        assert_eq!(
+            builder().widths([100, 200, 100]).build(),
+            Err(BuildError::Duplicate { value: 100.0 })
+        );
+        assert_eq!(
+            SrcsetBuilder::new(|_| String::new())
+                .widths([100])
+                .to_srcset_string(),
+            Err(BuildError::Serialize(SerializeError::EmptyUrl { index: 0 }))
+        );
+    }
+}
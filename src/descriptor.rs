@@ -0,0 +1,261 @@
+//! Candidates whose descriptor is a single enum, so that impossible combinations
+//! like a width together with a density cannot be represented.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::num::NonZeroU32;
+
+use crate::serialize::{number, write_url};
+use crate::spec::Tokenizer;
+use crate::ImageCandidate;
+
+/// The descriptor of a candidate.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Descriptor {
+    /// No descriptor, which browsers treat as `1x`.
+    None,
+    /// A `w` descriptor.
+    Width(NonZeroU32),
+    /// An `x` descriptor.
+    Density(f64),
+    /// A `w` descriptor followed by an `h` descriptor.
+    WidthHeight(NonZeroU32, NonZeroU32),
+    /// Descriptors that are not valid, as written in the `srcset`, e.g. `100w 2x`.
+    Unknown(String),
+}
+
+impl fmt::Display for Descriptor {
+    /// Writes the descriptor in `srcset` syntax; [`Descriptor::None`] writes nothing.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Descriptor::None => Ok(()),
+            Descriptor::Width(width) => write!(f, "{width}w"),
+            Descriptor::Density(density) => write!(f, "{}x", number(*density)),
+            Descriptor::WidthHeight(width, height) => write!(f, "{width}w {height}h"),
+            Descriptor::Unknown(text) => f.write_str(text),
+        }
+    }
+}
+
+/// A single candidate in a `srcset`, with a typed [`Descriptor`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    pub url: String,
+    pub descriptor: Descriptor,
+}
+
+impl fmt::Display for Candidate {
+    /// Writes the candidate in `srcset` syntax, e.g. `cat.jpg 400w 300h`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_url(f, &self.url)?;
+        match self.descriptor {
+            Descriptor::None => Ok(()),
+            ref descriptor => write!(f, " {descriptor}"),
+        }
+    }
+}
+
+/// Converts a descriptor value to a positive integer, if it is one that fits.
+fn to_non_zero_u32(value: f64) -> Option<NonZeroU32> {
+    // `as` saturates, so values out of range don't convert back to themselves.
+    let integer = value as u32;
+    (f64::from(integer) == value)
+        .then_some(integer)
+        .and_then(NonZeroU32::new)
+}
+
+impl ImageCandidate {
+    /// The candidate's descriptors, as a [`Descriptor`].
+    ///
+    /// Combinations the enum cannot represent, such as a width and a density, or a
+    /// width or height that is not a positive integer fitting in a `u32`, become
+    /// [`Descriptor::Unknown`].
+    ///
+    /// # Examples
+    /// ```
+    /// use std::num::NonZeroU32;
+    /// use srcset_parse::Descriptor;
+    ///
+    /// let candidates = srcset_parse::parse("a.png 400w, b.png 1.5x, c.png");
+    /// let descriptors: Vec<_> = candidates.iter().map(|c| c.descriptor()).collect();
+    /// let width = NonZeroU32::new(400).unwrap();
+    /// assert_eq!(
+    ///     descriptors,
+    ///     vec![Descriptor::Width(width), Descriptor::Density(1.5), Descriptor::None]
+    /// );
+    /// ```
+    pub fn descriptor(&self) -> Descriptor {
+        let integers = (
+            self.width.map(to_non_zero_u32),
+            self.density,
+            self.height.map(to_non_zero_u32),
+        );
+        match integers {
+            (None, None, None) => Descriptor::None,
+            (Some(Some(width)), None, None) => Descriptor::Width(width),
+            (None, Some(density), None) => Descriptor::Density(density),
+            (Some(Some(width)), None, Some(Some(height))) => Descriptor::WidthHeight(width, height),
+            _ => {
+                let descriptors = [(self.width, 'w'), (self.height, 'h'), (self.density, 'x')];
+                let text = descriptors
+                    .iter()
+                    .filter_map(|&(value, unit)| Some(format!("{}{unit}", number(value?))))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Descriptor::Unknown(text)
+            }
+        }
+    }
+}
+
+impl From<ImageCandidate> for Candidate {
+    fn from(candidate: ImageCandidate) -> Self {
+        Self {
+            descriptor: candidate.descriptor(),
+            url: candidate.url,
+        }
+    }
+}
+
+/// Converts back to an [`ImageCandidate`]. Fails, returning the candidate, if its
+/// descriptor is [`Descriptor::Unknown`].
+impl TryFrom<Candidate> for ImageCandidate {
+    type Error = Candidate;
+
+    fn try_from(candidate: Candidate) -> Result<Self, Self::Error> {
+        let (width, density, height) = match candidate.descriptor {
+            Descriptor::None => (None, None, None),
+            Descriptor::Width(width) => (Some(f64::from(width.get())), None, None),
+            Descriptor::Density(density) => (None, Some(density), None),
+            Descriptor::WidthHeight(width, height) => (
+                Some(f64::from(width.get())),
+                None,
+                Some(f64::from(height.get())),
+            ),
+            Descriptor::Unknown(_) => return Err(candidate),
+        };
+        Ok(ImageCandidate {
+            url: candidate.url,
+            width,
+            density,
+            height,
+        })
+    }
+}
+
+/// Parses an `srcset` string following the HTML standard's algorithm, like
+/// [`crate::parse_spec`], but keeps candidates with invalid descriptors as
+/// [`Descriptor::Unknown`] instead of dropping them.
+///
+/// # Examples
+/// ```
+/// use std::num::NonZeroU32;
+/// use srcset_parse::{parse_candidates, Descriptor};
+///
+/// let candidates = parse_candidates("a.png 400w 300h, b.png 1x 2x");
+/// let (width, height) = (NonZeroU32::new(400).unwrap(), NonZeroU32::new(300).unwrap());
+/// assert_eq!(candidates[0].descriptor, Descriptor::WidthHeight(width, height));
+/// assert_eq!(candidates[1].descriptor, Descriptor::Unknown("1x 2x".to_string()));
+/// ```
+pub fn parse_candidates(srcset: &str) -> Vec<Candidate> {
+    Tokenizer::new(srcset)
+        .map(|raw| {
+            let descriptor = match raw.parse() {
+                Ok(candidate) => candidate.descriptor(),
+                Err(_) => {
+                    let texts: Vec<_> = raw.descriptors.iter().map(|d| d.text).collect();
+                    Descriptor::Unknown(texts.join(" "))
+                }
+            };
+            Candidate {
+                url: raw.url.text.to_string(),
+                descriptor,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::num::NonZeroU32;
+
+    use super::{parse_candidates, Candidate, Descriptor};
+    use crate::tests::candidate;
+    use crate::{parse, ImageCandidate};
+
+    #[test]
+    fn represents_invalid_combinations_as_unknown() {
+        assert_eq!(
+            candidate("a.png", Some(100.0), Some(2.0)).descriptor(),
+            Descriptor::Unknown("100w 2x".to_string())
+        );
+        assert_eq!(
+            candidate("a.png", Some(1.5), None).descriptor(),
+            Descriptor::Unknown("1.5w".to_string())
+        );
+        assert_eq!(
+            ImageCandidate {
+                height: Some(100.0),
+                ..candidate("a.png", None, None)
+            }
+            .descriptor(),
+            Descriptor::Unknown("100h".to_string())
+        );
+        assert_eq!(
+            parse("a.png 100w 50h")[0].descriptor(),
+            Descriptor::WidthHeight(NonZeroU32::new(100).unwrap(), NonZeroU32::new(50).unwrap())
+        );
+    }
+
+    #[test]
+    fn represents_widths_that_are_not_positive_integers_as_unknown() {
+        for (width, text) in [
+            (0.0, "0w"),
+            (-100.0, "-100w"),
+            (5_000_000_000.0, "5000000000w"),
+            (f64::NAN, "NaNw"),
+        ] {
+            assert_eq!(
+                candidate("a.png", Some(width), None).descriptor(),
+                Descriptor::Unknown(text.to_string())
+            );
+        }
+        assert_eq!(
+            ImageCandidate {
+                height: Some(0.0),
+                ..candidate("a.png", Some(100.0), None)
+            }
+            .descriptor(),
+            Descriptor::Unknown("100w 0h".to_string())
+        );
+
+        let candidates = parse_candidates("a.png 5000000000w, b.png 4294967295w");
+        assert_eq!(candidates[0].to_string(), "a.png 5000000000w");
+        assert_eq!(candidates[1].descriptor, Descriptor::Width(NonZeroU32::MAX));
+    }
+
+    #[test]
+    fn converts_both_ways() {
+        for original in parse("a.png, a.png 100w, a.png 1.5x, a.png 100w 50h") {
+            let converted = Candidate::from(original.clone());
+            assert_eq!(ImageCandidate::try_from(converted), Ok(original));
+        }
+
+        let unknown = Candidate::from(candidate("a.png", Some(100.0), Some(2.0)));
+        assert_eq!(ImageCandidate::try_from(unknown.clone()), Err(unknown));
+    }
+
+    #[test]
+    fn keeps_candidates_with_invalid_descriptors() {
+        let candidates = parse_candidates("a.png, b.png 1.5w, c.png 2x");
+        let formatted: Vec<_> = candidates.iter().map(Candidate::to_string).collect();
+        assert_eq!(formatted, vec!["a.png", "b.png 1.5w", "c.png 2x"]);
+        assert_eq!(candidates[0].descriptor, Descriptor::None);
+        assert_eq!(candidates[2].descriptor, Descriptor::Density(2.0));
+    }
+}
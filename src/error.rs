@@ -0,0 +1,322 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+/// The kind of problem a [`Diagnostic`] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorKind {
+    /// Commas that don't separate two candidates, e.g. `a.png,, b.png` or a leading `,`.
+    UnexpectedComma,
+    /// A descriptor that can't be combined with an earlier one, e.g. `100w 2x` or `1x 1x`.
+    ConflictingDescriptors,
+    /// A `w` descriptor whose value is not a positive integer.
+    InvalidWidth,
+    /// An `x` descriptor whose value is not a non-negative floating-point number.
+    InvalidDensity,
+    /// An `h` descriptor whose value is not a positive integer.
+    InvalidHeight,
+    /// An `h` descriptor without a `w` descriptor.
+    HeightWithoutWidth,
+    /// A descriptor that is not `w`, `x` or `h`.
+    UnknownDescriptor,
+}
+
+impl ErrorKind {
+    /// Whether the problem causes the candidate it belongs to to be dropped.
+    pub fn drops_candidate(self) -> bool {
+        self != ErrorKind::UnexpectedComma
+    }
+}
+
+/// A problem found while parsing a `srcset`, with the byte range of the input it
+/// refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub kind: ErrorKind,
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(kind: ErrorKind, span: Range<usize>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl core::error::Error for Diagnostic {}
+
+/// The error returned by [`crate::try_parse`]. Holds every problem found in the
+/// input, in order; there is always at least one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "ParseErrorData"))]
+pub struct ParseError {
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// The serialized form of a [`ParseError`], which may have no diagnostics.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ParseErrorData {
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ParseErrorData> for ParseError {
+    type Error = &'static str;
+
+    fn try_from(data: ParseErrorData) -> Result<Self, Self::Error> {
+        if data.diagnostics.is_empty() {
+            return Err("a parse error needs at least one diagnostic");
+        }
+        Ok(Self::new(data.diagnostics))
+    }
+}
+
+impl ParseError {
+    pub(crate) fn new(diagnostics: Vec<Diagnostic>) -> Self {
+        debug_assert!(!diagnostics.is_empty());
+        Self { diagnostics }
+    }
+
+    /// The kind of the first problem found.
+    pub fn kind(&self) -> ErrorKind {
+        self.diagnostics[0].kind
+    }
+
+    /// All the problems found, in input order.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid srcset: {}", self.diagnostics[0])?;
+        match self.diagnostics.len() {
+            1 => Ok(()),
+            2 => write!(f, " (and 1 more problem)"),
+            n => write!(f, " (and {} more problems)", n - 1),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// The error returned when a candidate list can't be written as a valid `srcset`.
+/// Each variant holds the index of the offending candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SerializeError {
+    /// The candidate's URL is empty.
+    EmptyUrl { index: usize },
+    /// The candidate has both a width and a density.
+    ConflictingDescriptors { index: usize },
+    /// The candidate's width is not a positive integer.
+    InvalidWidth { index: usize },
+    /// The candidate's density is negative or not finite.
+    InvalidDensity { index: usize },
+    /// The candidate's height is not a positive integer, or there is no width.
+    InvalidHeight { index: usize },
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::EmptyUrl { index } => write!(f, "candidate {index} has an empty URL"),
+            SerializeError::ConflictingDescriptors { index } => {
+                write!(f, "candidate {index} has both a width and a density")
+            }
+            SerializeError::InvalidWidth { index } => {
+                write!(
+                    f,
+                    "candidate {index} has a width that is not a positive integer"
+                )
+            }
+            SerializeError::InvalidDensity { index } => {
+                write!(f, "candidate {index} has a negative or non-finite density")
+            }
+            SerializeError::InvalidHeight { index } => write!(
+                f,
+                "candidate {index} has a height that is not a positive integer, or no width"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for SerializeError {}
+
+/// The error returned by [`crate::SrcsetBuilder`] for an invalid ladder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BuildError {
+    /// No widths or densities were given.
+    Empty,
+    /// Both widths and densities were given.
+    MixedDescriptors,
+    /// A width, density or base width is zero, negative or not a number.
+    NonPositive { value: f64 },
+    /// The same width or density was given twice.
+    Duplicate { value: f64 },
+    /// A width ladder's URL template contains `{density}`, which only a density
+    /// ladder can fill in.
+    DensityInWidthTemplate,
+    /// Densities were added for two different base widths.
+    ConflictingBaseWidths { first: u32, second: u32 },
+    /// A generated candidate can't be written as a `srcset`.
+    Serialize(SerializeError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Empty => write!(f, "no widths or densities were given"),
+            BuildError::MixedDescriptors => {
+                write!(f, "widths and densities can't be mixed in one srcset")
+            }
+            BuildError::NonPositive { value } => write!(f, "{value} is not a positive number"),
+            BuildError::Duplicate { value } => write!(f, "{value} was given more than once"),
+            BuildError::DensityInWidthTemplate => {
+                write!(
+                    f,
+                    "the URL template of a width ladder can't use {{density}}"
+                )
+            }
+            BuildError::ConflictingBaseWidths { first, second } => {
+                write!(
+                    f,
+                    "densities were given for base widths {first} and {second}"
+                )
+            }
+            BuildError::Serialize(error) => error.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            BuildError::Serialize(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when a candidate URL can't be resolved against a base URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UrlError {
+    /// The base URL is not an absolute URL.
+    InvalidBase,
+    /// The base URL can't have relative URLs resolved against it, e.g. `data:` URLs.
+    CannotBeABase,
+    /// The URL has a special scheme such as `https`, but no host.
+    EmptyHost,
+    /// The URL is absolute, or scheme-relative, but can't be parsed.
+    InvalidUrl,
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::InvalidBase => write!(f, "the base URL is not an absolute URL"),
+            UrlError::CannotBeABase => {
+                write!(f, "relative URLs can't be resolved against the base URL")
+            }
+            UrlError::EmptyHost => write!(f, "the URL has no host"),
+            UrlError::InvalidUrl => write!(f, "the URL is not a valid URL"),
+        }
+    }
+}
+
+impl core::error::Error for UrlError {}
+
+/// The error returned by [`crate::parse_image_set`]. Each variant except
+/// `NotAnImageSet` holds the index of the offending option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImageSetError {
+    /// The value is not an `image-set()` or `-webkit-image-set()` function.
+    NotAnImageSet,
+    /// The option is empty, e.g. in `image-set(a.png 1x, , b.png 2x)`.
+    EmptyOption { index: usize },
+    /// The option's image is not a `url()` or a string.
+    UnsupportedImage { index: usize },
+    /// The option's resolution is not a non-negative `x`, `dppx`, `dpi` or `dpcm`
+    /// value, or is given twice.
+    InvalidResolution { index: usize },
+    /// The option's `type()` is not a single string, or is given twice.
+    InvalidType { index: usize },
+}
+
+impl fmt::Display for ImageSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageSetError::NotAnImageSet => write!(f, "the value is not an image-set()"),
+            ImageSetError::EmptyOption { index } => write!(f, "option {index} is empty"),
+            ImageSetError::UnsupportedImage { index } => {
+                write!(
+                    f,
+                    "option {index} has an image that is not a url() or a string"
+                )
+            }
+            ImageSetError::InvalidResolution { index } => {
+                write!(f, "option {index} has an invalid resolution")
+            }
+            ImageSetError::InvalidType { index } => {
+                write!(f, "option {index} has an invalid type()")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ImageSetError {}
+
+/// The error returned by [`crate::length::parse_length`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LengthError {
+    /// The value is not a dimension or a math function, or is malformed.
+    Invalid,
+    /// The value uses a percentage, which a source size can't resolve.
+    Percentage,
+    /// A dimension has a unit that is not a length unit, e.g. `10deg`.
+    UnknownUnit,
+    /// The value is well formed but is not a length, e.g. `calc(2)` or
+    /// `calc(1px * 1px)`.
+    NotALength,
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthError::Invalid => write!(f, "the value is not a valid length"),
+            LengthError::Percentage => write!(f, "percentages are not allowed"),
+            LengthError::UnknownUnit => write!(f, "the value has an unknown unit"),
+            LengthError::NotALength => write!(f, "the value does not evaluate to a length"),
+        }
+    }
+}
+
+impl core::error::Error for LengthError {}
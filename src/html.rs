@@ -0,0 +1,371 @@
+//! Extracting the `srcset`s of an HTML document.
+//!
+//! The scanner follows the HTML standard's tokenizer closely enough to find start
+//! tags and their attributes: it skips comments, doctypes, end tags and the contents
+//! of raw text elements like `<script>`, and decodes the common character references
+//! in attribute values. It does not build a tree.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{parse, ImageCandidate};
+
+/// Elements whose contents are not markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "script", "style", "textarea", "title", "xmp", "iframe", "noembed", "noframes",
+];
+
+/// Named character references decoded in attribute values.
+const NAMED_REFERENCES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{A0}'),
+];
+
+/// A `srcset` found in an HTML document.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExtractedSrcset {
+    /// The element name, in lowercase: `img`, `source` or `link`.
+    pub element: String,
+    /// The attribute value, with character references decoded.
+    pub value: String,
+    /// The byte range of the attribute value in the document, without quotes.
+    pub span: Range<usize>,
+    /// The `sizes` attribute, or `imagesizes` on a `<link>`.
+    pub sizes: Option<String>,
+    /// The `src` attribute, or `href` on a `<link>`.
+    pub src: Option<String>,
+    /// The `media` attribute.
+    pub media: Option<String>,
+    /// The `type` attribute.
+    pub mime_type: Option<String>,
+    /// The candidates of the `srcset`, parsed with [`crate::parse`].
+    pub candidates: Vec<ImageCandidate>,
+}
+
+/// An attribute of a start tag.
+#[derive(Debug)]
+struct Attribute {
+    /// The name, in lowercase.
+    name: String,
+    value: String,
+    span: Range<usize>,
+}
+
+/// A start tag.
+#[derive(Debug)]
+struct Tag {
+    /// The name, in lowercase.
+    name: String,
+    attributes: Vec<Attribute>,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        self.attribute(name).map(|a| a.value.clone())
+    }
+
+    /// Extracts the `srcset` of the tag, if it is an element that has one.
+    fn extract(&self) -> Option<ExtractedSrcset> {
+        let (srcset, sizes, src) = match self.name.as_str() {
+            "img" | "source" => ("srcset", "sizes", "src"),
+            "link" => {
+                let rel = self.attribute("rel")?;
+                let preload = rel
+                    .value
+                    .split_ascii_whitespace()
+                    .any(|token| token.eq_ignore_ascii_case("preload"));
+                if !preload {
+                    return None;
+                }
+                ("imagesrcset", "imagesizes", "href")
+            }
+            _ => return None,
+        };
+
+        let attribute = self.attribute(srcset)?;
+        Some(ExtractedSrcset {
+            element: self.name.clone(),
+            value: attribute.value.clone(),
+            span: attribute.span.clone(),
+            sizes: self.value(sizes),
+            src: self.value(src),
+            media: self.value("media"),
+            mime_type: self.value("type"),
+            candidates: parse(&attribute.value),
+        })
+    }
+}
+
+/// Whitespace as defined by the HTML tokenizer.
+fn is_html_whitespace(b: u8) -> bool {
+    matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+/// Decodes the character references in an attribute value. Unknown references
+/// are kept as written.
+fn decode_references(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let reference = rest[1..].split_once(';').and_then(|(name, _)| {
+            let c = match name.strip_prefix('#') {
+                Some(number) => {
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+                None => NAMED_REFERENCES.iter().find(|(n, _)| *n == name)?.1,
+            };
+            Some((c, name.len() + 2))
+        });
+
+        match reference {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Scans an HTML document for start tags.
+struct Scanner<'a> {
+    html: &'a str,
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn byte(&self) -> Option<u8> {
+        self.html.as_bytes().get(self.position).copied()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.html[self.position..]
+    }
+
+    /// Moves past the next occurrence of `needle`, or to the end of the document.
+    fn skip_past(&mut self, needle: &str) {
+        self.position = match self.rest().find(needle) {
+            Some(index) => self.position + index + needle.len(),
+            None => self.html.len(),
+        };
+    }
+
+    /// Moves past the end tag of a raw text element, matching its name in any case.
+    fn skip_raw_text(&mut self, name: &str) {
+        let bytes = self.html.as_bytes();
+        while let Some(index) = self.rest().find("</") {
+            let start = self.position + index + 2;
+            let end = start + name.len();
+            self.position = start;
+            let closes = bytes
+                .get(start..end)
+                .is_some_and(|n| n.eq_ignore_ascii_case(name.as_bytes()))
+                && bytes
+                    .get(end)
+                    .is_none_or(|&b| is_html_whitespace(b) || b == b'/' || b == b'>');
+            if closes {
+                self.skip_past(">");
+                return;
+            }
+        }
+        self.position = self.html.len();
+    }
+
+    /// Takes bytes while `pred` holds, returning them.
+    fn take_while(&mut self, pred: impl Fn(u8) -> bool) -> &'a str {
+        let start = self.position;
+        while self.byte().is_some_and(&pred) {
+            self.position += 1;
+        }
+        // Only ever stops at an ASCII byte or the end of the document.
+        &self.html[start..self.position]
+    }
+
+    /// Reads the attributes of a start tag, up to and including its `>`.
+    fn attributes(&mut self) -> Vec<Attribute> {
+        let mut attributes: Vec<Attribute> = Vec::new();
+
+        loop {
+            self.take_while(|b| is_html_whitespace(b) || b == b'/');
+            match self.byte() {
+                None => break,
+                Some(b'>') => {
+                    self.position += 1;
+                    break;
+                }
+                _ => {}
+            }
+
+            // An attribute name may start with `=`.
+            let start = self.position;
+            self.position += self.rest().chars().next().map_or(1, char::len_utf8);
+            self.take_while(|b| !is_html_whitespace(b) && !matches!(b, b'/' | b'>' | b'='));
+            let name = self.html[start..self.position].to_ascii_lowercase();
+
+            self.take_while(is_html_whitespace);
+            let (value, span) = if self.byte() == Some(b'=') {
+                self.position += 1;
+                self.take_while(is_html_whitespace);
+                match self.byte() {
+                    Some(quote @ (b'"' | b'\'')) => {
+                        self.position += 1;
+                        let value = self.take_while(|b| b != quote);
+                        let span = self.position - value.len()..self.position;
+                        if self.byte().is_some() {
+                            self.position += 1;
+                        }
+                        (value, span)
+                    }
+                    _ => {
+                        let value = self.take_while(|b| !is_html_whitespace(b) && b != b'>');
+                        (value, self.position - value.len()..self.position)
+                    }
+                }
+            } else {
+                ("", self.position..self.position)
+            };
+
+            // Later duplicates are ignored, as in browsers.
+            if attributes.iter().all(|a| a.name != name) {
+                attributes.push(Attribute {
+                    name,
+                    value: decode_references(value),
+                    span,
+                });
+            }
+        }
+
+        attributes
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Tag;
+
+    fn next(&mut self) -> Option<Tag> {
+        loop {
+            self.skip_past("<");
+            let rest = self.rest();
+            if rest.is_empty() {
+                return None;
+            }
+
+            if rest.starts_with("!--") {
+                self.skip_past("-->");
+            } else if rest.starts_with(['!', '/', '?']) {
+                self.skip_past(">");
+            } else if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                let name = self
+                    .take_while(|b| !is_html_whitespace(b) && b != b'/' && b != b'>')
+                    .to_ascii_lowercase();
+                let attributes = self.attributes();
+                if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                    self.skip_raw_text(&name);
+                }
+                return Some(Tag { name, attributes });
+            }
+        }
+    }
+}
+
+/// Finds every `srcset` of an HTML document: the `srcset` of `<img>` and
+/// `<source>` elements, and the `imagesrcset` of `<link rel=preload>` elements, in
+/// document order.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::html::extract_srcsets;
+///
+/// let html = r#"<picture>
+///   <source srcset="cat.webp 1x, cat@2x.webp 2x" type="image/webp">
+///   <img src="cat.jpg" srcset="cat.jpg 1x, cat@2x.jpg 2x" alt="A cat">
+/// </picture>"#;
+///
+/// let srcsets = extract_srcsets(html);
+/// assert_eq!(srcsets.len(), 2);
+/// assert_eq!(srcsets[0].element, "source");
+/// assert_eq!(srcsets[0].mime_type.as_deref(), Some("image/webp"));
+/// assert_eq!(&html[srcsets[1].span.clone()], "cat.jpg 1x, cat@2x.jpg 2x");
+/// assert_eq!(srcsets[1].candidates[1].url, "cat@2x.jpg");
+/// ```
+pub fn extract_srcsets(html: &str) -> Vec<ExtractedSrcset> {
+    let scanner = Scanner { html, position: 0 };
+    scanner.filter_map(|tag| tag.extract()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{decode_references, extract_srcsets};
+
+    #[test]
+    fn extracts_preload_links() {
+        let html = "<LINK rel='Preload' as=image imagesrcset='a.png 1x, b.png 2x' \
+                    imagesizes=50vw href=a.png><link rel=stylesheet imagesrcset=c.png>";
+        let srcsets = extract_srcsets(html);
+        assert_eq!(srcsets.len(), 1);
+        assert_eq!(srcsets[0].element, "link");
+        assert_eq!(srcsets[0].sizes.as_deref(), Some("50vw"));
+        assert_eq!(srcsets[0].src.as_deref(), Some("a.png"));
+        assert_eq!(srcsets[0].candidates.len(), 2);
+    }
+
+    #[test]
+    fn skips_comments_and_raw_text() {
+        let html = r#"<!-- <img srcset="comment.png"> -->
+            <script>document.write('<img srcset="script.png">')</script >
+            <textarea><img srcset="textarea.png"></TEXTAREA>
+            <img srcset=real.png sizes = "100vw" srcset="duplicate.png">"#;
+        let srcsets = extract_srcsets(html);
+        assert_eq!(srcsets.len(), 1);
+        assert_eq!(srcsets[0].value, "real.png");
+        assert_eq!(&html[srcsets[0].span.clone()], "real.png");
+        assert_eq!(srcsets[0].sizes.as_deref(), Some("100vw"));
+    }
+
+    #[test]
+    fn decodes_character_references() {
+        let html = r#"<img srcset="a.png?x=1&amp;y=2 1x, b&#44;c.png 2x">"#;
+        let srcsets = extract_srcsets(html);
+        assert_eq!(srcsets[0].value, "a.png?x=1&y=2 1x, b,c.png 2x");
+        assert_eq!(srcsets[0].candidates[0].url, "a.png?x=1&y=2");
+
+        assert_eq!(
+            decode_references("&#x1F600;&unknown;&"),
+            "\u{1F600}&unknown;&"
+        );
+    }
+
+    #[test]
+    fn handles_truncated_documents() {
+        assert_eq!(extract_srcsets("<img srcset=\"a.png")[0].value, "a.png");
+        assert_eq!(extract_srcsets("<img srcset")[0].span, 11..11);
+        assert!(extract_srcsets("<!-- <img srcset=a.png>").is_empty());
+        assert!(extract_srcsets("<").is_empty());
+        assert_eq!(extract_srcsets("<img é=1 srcset=a.png")[0].value, "a.png");
+    }
+}
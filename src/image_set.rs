@@ -0,0 +1,330 @@
+//! Parsing of the CSS [`image-set()`](https://drafts.csswg.org/css-images-4/#image-set-notation)
+//! function, the stylesheet counterpart of `srcset`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::lex::{is_css_whitespace, is_non_negative_number, split_top_level_commas};
+use crate::{ImageCandidate, ImageSetError};
+
+/// The function names `image-set()` is written with.
+const FUNCTION_NAMES: &[&str] = &["image-set(", "-webkit-image-set("];
+
+/// The resolution units, longest first, with the number of `dppx` each is worth.
+const RESOLUTION_UNITS: &[(&str, f64)] = &[
+    ("dppx", 1.0),
+    ("dpcm", 2.54 / 96.0),
+    ("dpi", 1.0 / 96.0),
+    ("x", 1.0),
+];
+
+/// An option of an `image-set()`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageSetOption {
+    /// The image, with its resolution as a density. An option without a
+    /// resolution is `1x`.
+    pub candidate: ImageCandidate,
+    /// The MIME type given with `type()`.
+    pub mime_type: Option<String>,
+}
+
+/// Strips `prefix` from the start of `input`, ignoring ASCII case.
+fn strip_prefix_ignore_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = input.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &input[prefix.len()..])
+}
+
+/// Consumes a CSS escape, after its backslash, pushing the escaped character.
+fn consume_escape<'a>(input: &'a str, out: &mut String) -> &'a str {
+    let hex_len = input
+        .bytes()
+        .take(6)
+        .take_while(u8::is_ascii_hexdigit)
+        .count();
+    if hex_len == 0 {
+        let mut chars = input.chars();
+        match chars.next() {
+            // An escaped newline is a line continuation.
+            Some('\n') => {}
+            Some(c) => out.push(c),
+            None => out.push(char::REPLACEMENT_CHARACTER),
+        }
+        return chars.as_str();
+    }
+
+    let code = u32::from_str_radix(&input[..hex_len], 16).unwrap_or_default();
+    let c = match char::from_u32(code) {
+        Some('\0') | None => char::REPLACEMENT_CHARACTER,
+        Some(c) => c,
+    };
+    out.push(c);
+    let rest = &input[hex_len..];
+    rest.strip_prefix(is_css_whitespace).unwrap_or(rest)
+}
+
+/// Consumes a quoted CSS string, returning its value and the rest of the input.
+fn consume_string(input: &str) -> Option<(String, &str)> {
+    let quote = input.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let mut rest = &input[1..];
+    let mut value = String::new();
+
+    loop {
+        let mut chars = rest.chars();
+        match chars.next()? {
+            c if c == quote => return Some((value, chars.as_str())),
+            '\n' => return None,
+            '\\' => rest = consume_escape(chars.as_str(), &mut value),
+            c => {
+                value.push(c);
+                rest = chars.as_str();
+            }
+        }
+    }
+}
+
+/// Consumes a `url()`, after its opening parenthesis, returning the URL and the
+/// rest of the input.
+fn consume_url(input: &str) -> Option<(String, &str)> {
+    let input = input.trim_start_matches(is_css_whitespace);
+    if input.starts_with(['"', '\'']) {
+        let (url, rest) = consume_string(input)?;
+        let rest = rest
+            .trim_start_matches(is_css_whitespace)
+            .strip_prefix(')')?;
+        return Some((url, rest));
+    }
+
+    let mut url = String::new();
+    let mut rest = input;
+    loop {
+        let mut chars = rest.chars();
+        match chars.next()? {
+            ')' => return Some((url, chars.as_str())),
+            c if is_css_whitespace(c) => {
+                let rest = rest
+                    .trim_start_matches(is_css_whitespace)
+                    .strip_prefix(')')?;
+                return Some((url, rest));
+            }
+            '"' | '\'' | '(' => return None,
+            '\\' => rest = consume_escape(chars.as_str(), &mut url),
+            c => {
+                url.push(c);
+                rest = chars.as_str();
+            }
+        }
+    }
+}
+
+/// Parses a `<resolution>`, returning it in `dppx`.
+pub(crate) fn parse_resolution(token: &str) -> Option<f64> {
+    RESOLUTION_UNITS.iter().find_map(|&(unit, dppx)| {
+        let number = token.get(..token.len().checked_sub(unit.len())?)?;
+        let suffix = &token[number.len()..];
+        if !suffix.eq_ignore_ascii_case(unit) || !is_non_negative_number(number) {
+            return None;
+        }
+        let value = number.parse::<f64>().ok()? * dppx;
+        value.is_finite().then_some(value)
+    })
+}
+
+/// Parses one option of an `image-set()`.
+fn parse_option(option: &str, index: usize) -> Result<ImageSetOption, ImageSetError> {
+    let option = option.trim_matches(is_css_whitespace);
+    if option.is_empty() {
+        return Err(ImageSetError::EmptyOption { index });
+    }
+
+    let unsupported = ImageSetError::UnsupportedImage { index };
+    let (url, mut rest) = if option.starts_with(['"', '\'']) {
+        consume_string(option).ok_or(unsupported)?
+    } else {
+        let args = strip_prefix_ignore_case(option, "url(").ok_or(unsupported)?;
+        consume_url(args).ok_or(unsupported)?
+    };
+
+    let mut density = None;
+    let mut mime_type = None;
+    loop {
+        // Components need no whitespace between them where the tokens end
+        // anyway, as in `"a.png"2x`.
+        let trimmed = rest.trim_start_matches(is_css_whitespace);
+        if trimmed.is_empty() {
+            break;
+        }
+
+        if let Some(args) = strip_prefix_ignore_case(trimmed, "type(") {
+            let invalid = ImageSetError::InvalidType { index };
+            let args = args.trim_start_matches(is_css_whitespace);
+            let (value, after) = consume_string(args).ok_or(invalid)?;
+            let after = after.trim_start_matches(is_css_whitespace);
+            rest = after.strip_prefix(')').ok_or(invalid)?;
+            if mime_type.replace(value).is_some() {
+                return Err(invalid);
+            }
+        } else {
+            // The resolution token ends before whitespace, a string or a
+            // parenthesis; `2xtype(` is a single, invalid token.
+            let end = trimmed
+                .find(|c| is_css_whitespace(c) || matches!(c, '"' | '\'' | '(' | ')'))
+                .unwrap_or(trimmed.len());
+            let invalid = ImageSetError::InvalidResolution { index };
+            let resolution = parse_resolution(&trimmed[..end]).ok_or(invalid)?;
+            rest = &trimmed[end..];
+            if density.replace(resolution).is_some() {
+                return Err(invalid);
+            }
+        }
+    }
+
+    Ok(ImageSetOption {
+        candidate: ImageCandidate {
+            url,
+            width: None,
+            density: Some(density.unwrap_or(1.0)),
+            height: None,
+        },
+        mime_type,
+    })
+}
+
+/// Parses a CSS `image-set()` or `-webkit-image-set()` value.
+///
+/// Each option's image must be a `url()` or a string. Resolutions in `x`, `dppx`,
+/// `dpi` and `dpcm` are converted to densities, so `192dpi` becomes `2x`.
+///
+/// # Examples
+/// ```
+/// let options = srcset_parse::parse_image_set(
+///     r#"image-set(url("cat.avif") 2x type("image/avif"), "cat.jpg" 192dpi)"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(options[0].candidate.url, "cat.avif");
+/// assert_eq!(options[0].mime_type.as_deref(), Some("image/avif"));
+/// assert_eq!(options[1].candidate.density, Some(2.0));
+/// ```
+pub fn parse_image_set(value: &str) -> Result<Vec<ImageSetOption>, ImageSetError> {
+    let value = value.trim_matches(is_css_whitespace);
+    let args = FUNCTION_NAMES
+        .iter()
+        .find_map(|name| strip_prefix_ignore_case(value, name))
+        .and_then(|args| args.strip_suffix(')'))
+        .ok_or(ImageSetError::NotAnImageSet)?;
+
+    split_top_level_commas(args)
+        .into_iter()
+        .enumerate()
+        .map(|(index, option)| parse_option(option, index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::parse_image_set;
+    use crate::ImageSetError;
+
+    fn densities(value: &str) -> Vec<(String, f64)> {
+        parse_image_set(value)
+            .unwrap()
+            .into_iter()
+            .map(|o| (o.candidate.url, o.candidate.density.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn normalizes_resolutions_to_densities() {
+        assert_eq!(
+            densities("-webkit-image-set(url(a.png) 1X, url( 'b.png' ) 2dppx, 'c.png' 96dpi)"),
+            vec![
+                ("a.png".to_string(), 1.0),
+                ("b.png".to_string(), 2.0),
+                ("c.png".to_string(), 1.0),
+            ]
+        );
+        let dpcm = densities("image-set('a.png' 75.59055dpcm)")[0].1;
+        assert!((dpcm - 2.0).abs() < 1e-6);
+        assert_eq!(densities("IMAGE-SET(\"a.png\")")[0].1, 1.0);
+    }
+
+    #[test]
+    fn needs_no_whitespace_between_tokens() {
+        assert_eq!(
+            densities("image-set('a.png'1x, url(b.png)2x, \"c.png\"type('image/png')3x)"),
+            vec![
+                ("a.png".to_string(), 1.0),
+                ("b.png".to_string(), 2.0),
+                ("c.png".to_string(), 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn unescapes_urls() {
+        assert_eq!(
+            densities(r#"image-set(url(a\ b\).png) 1e1x, "c\"d\26 .png" 3x)"#),
+            vec![
+                ("a b).png".to_string(), 10.0),
+                ("c\"d&.png".to_string(), 3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_types_after_the_image() {
+        let options = parse_image_set(
+            "image-set(type('image/webp') 'a.webp' 2x, 'b.jpg' type( \"image/jpeg\" ))",
+        );
+        assert!(options.is_err());
+
+        let options = parse_image_set(
+            "image-set('a.webp' type('image/webp') 2x, 'b.jpg' type( \"image/jpeg\" ))",
+        )
+        .unwrap();
+        assert_eq!(options[0].mime_type.as_deref(), Some("image/webp"));
+        assert_eq!(options[0].candidate.density, Some(2.0));
+        assert_eq!(options[1].mime_type.as_deref(), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let error = |value| parse_image_set(value).unwrap_err();
+        assert_eq!(error("url(a.png)"), ImageSetError::NotAnImageSet);
+        assert_eq!(
+            error("image-set('a.png', )"),
+            ImageSetError::EmptyOption { index: 1 }
+        );
+        assert_eq!(
+            error("image-set(linear-gradient(red, blue) 1x)"),
+            ImageSetError::UnsupportedImage { index: 0 }
+        );
+        assert_eq!(
+            error("image-set(url(a b.png))"),
+            ImageSetError::UnsupportedImage { index: 0 }
+        );
+        assert_eq!(
+            error("image-set('a.png' -1x)"),
+            ImageSetError::InvalidResolution { index: 0 }
+        );
+        assert_eq!(
+            error("image-set('a.png' 1x 2x)"),
+            ImageSetError::InvalidResolution { index: 0 }
+        );
+        assert_eq!(
+            error("image-set('a.png' 2xtype('image/png'))"),
+            ImageSetError::InvalidResolution { index: 0 }
+        );
+        assert_eq!(
+            error("image-set('a.png' 2x'b.png')"),
+            ImageSetError::InvalidResolution { index: 0 }
+        );
+        assert_eq!(
+            error("image-set('a.png' type(image/png))"),
+            ImageSetError::InvalidType { index: 0 }
+        );
+    }
+}
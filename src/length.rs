@@ -0,0 +1,526 @@
+//! Parsing and evaluation of CSS lengths, as used in source sizes and media
+//! features.
+//!
+//! A length is a dimension such as `50vw`, or a math function such as
+//! `calc(100vw - 2rem)`, `min()`, `max()` or `clamp()`:
+//!
+//! ```
+//! use srcset_parse::length::parse_length;
+//! use srcset_parse::media::MediaEnvironment;
+//!
+//! let env = MediaEnvironment::new(1024.0, 768.0, 1.0);
+//! let length = parse_length("clamp(320px, calc(50vw - 2rem), 640px)").unwrap();
+//! assert_eq!(length.evaluate(&env), 480.0);
+//! ```
+//!
+//! Percentages are rejected: a source size has nothing to resolve them against.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::lex::is_css_whitespace;
+use crate::media::MediaEnvironment;
+use crate::LengthError;
+
+/// What a unit is relative to.
+#[derive(Debug, Clone, Copy)]
+enum Basis {
+    Absolute,
+    FontSize,
+    ViewportWidth,
+    ViewportHeight,
+    ViewportMin,
+    ViewportMax,
+}
+
+/// The length units, with what they are relative to and how much of it each is
+/// worth. Without font metrics, `ex` and `ch` are `0.5em` as CSS prescribes, and
+/// the other font-relative units use typical values. The small, large and
+/// dynamic viewport units all resolve against the viewport.
+const UNITS: &[(&str, Basis, f64)] = &[
+    ("px", Basis::Absolute, 1.0),
+    ("cm", Basis::Absolute, 96.0 / 2.54),
+    ("mm", Basis::Absolute, 96.0 / 25.4),
+    ("q", Basis::Absolute, 96.0 / 101.6),
+    ("in", Basis::Absolute, 96.0),
+    ("pt", Basis::Absolute, 96.0 / 72.0),
+    ("pc", Basis::Absolute, 16.0),
+    ("em", Basis::FontSize, 1.0),
+    ("rem", Basis::FontSize, 1.0),
+    ("ex", Basis::FontSize, 0.5),
+    ("rex", Basis::FontSize, 0.5),
+    ("ch", Basis::FontSize, 0.5),
+    ("rch", Basis::FontSize, 0.5),
+    ("cap", Basis::FontSize, 0.7),
+    ("rcap", Basis::FontSize, 0.7),
+    ("ic", Basis::FontSize, 1.0),
+    ("ric", Basis::FontSize, 1.0),
+    ("lh", Basis::FontSize, 1.2),
+    ("rlh", Basis::FontSize, 1.2),
+    ("vw", Basis::ViewportWidth, 0.01),
+    ("svw", Basis::ViewportWidth, 0.01),
+    ("lvw", Basis::ViewportWidth, 0.01),
+    ("dvw", Basis::ViewportWidth, 0.01),
+    ("vi", Basis::ViewportWidth, 0.01),
+    ("svi", Basis::ViewportWidth, 0.01),
+    ("lvi", Basis::ViewportWidth, 0.01),
+    ("dvi", Basis::ViewportWidth, 0.01),
+    ("vh", Basis::ViewportHeight, 0.01),
+    ("svh", Basis::ViewportHeight, 0.01),
+    ("lvh", Basis::ViewportHeight, 0.01),
+    ("dvh", Basis::ViewportHeight, 0.01),
+    ("vb", Basis::ViewportHeight, 0.01),
+    ("svb", Basis::ViewportHeight, 0.01),
+    ("lvb", Basis::ViewportHeight, 0.01),
+    ("dvb", Basis::ViewportHeight, 0.01),
+    ("vmin", Basis::ViewportMin, 0.01),
+    ("svmin", Basis::ViewportMin, 0.01),
+    ("lvmin", Basis::ViewportMin, 0.01),
+    ("dvmin", Basis::ViewportMin, 0.01),
+    ("vmax", Basis::ViewportMax, 0.01),
+    ("svmax", Basis::ViewportMax, 0.01),
+    ("lvmax", Basis::ViewportMax, 0.01),
+    ("dvmax", Basis::ViewportMax, 0.01),
+];
+
+/// A parsed CSS length.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Length {
+    /// A dimension, e.g. `50vw`, with its unit in lowercase. A unitless zero is
+    /// `0px`.
+    Dimension {
+        value: f64,
+        unit: String,
+    },
+    /// A number, which only appears inside math functions, e.g. the `2` in
+    /// `calc(100vw / 2)`.
+    Number(f64),
+    Sum(Box<Length>, Box<Length>),
+    Difference(Box<Length>, Box<Length>),
+    Product(Box<Length>, Box<Length>),
+    Quotient(Box<Length>, Box<Length>),
+    Min(Vec<Length>),
+    Max(Vec<Length>),
+    /// `clamp(min, value, max)`.
+    Clamp(Box<Length>, Box<Length>, Box<Length>),
+}
+
+impl Length {
+    /// Evaluates the length to CSS pixels in `env`. Font-relative units resolve
+    /// against [`MediaEnvironment::root_font_size`].
+    pub fn evaluate(&self, env: &MediaEnvironment) -> f64 {
+        match self {
+            Length::Dimension { value, unit } => {
+                let Some(&(_, basis, factor)) = UNITS.iter().find(|(u, ..)| u == unit) else {
+                    return f64::NAN;
+                };
+                let base = match basis {
+                    Basis::Absolute => 1.0,
+                    Basis::FontSize => env.root_font_size,
+                    Basis::ViewportWidth => env.viewport_width,
+                    Basis::ViewportHeight => env.viewport_height,
+                    Basis::ViewportMin => env.viewport_width.min(env.viewport_height),
+                    Basis::ViewportMax => env.viewport_width.max(env.viewport_height),
+                };
+                value * factor * base
+            }
+            Length::Number(value) => *value,
+            Length::Sum(a, b) => a.evaluate(env) + b.evaluate(env),
+            Length::Difference(a, b) => a.evaluate(env) - b.evaluate(env),
+            Length::Product(a, b) => a.evaluate(env) * b.evaluate(env),
+            Length::Quotient(a, b) => a.evaluate(env) / b.evaluate(env),
+            Length::Min(values) => values
+                .iter()
+                .map(|v| v.evaluate(env))
+                .fold(f64::INFINITY, f64::min),
+            Length::Max(values) => values
+                .iter()
+                .map(|v| v.evaluate(env))
+                .fold(f64::NEG_INFINITY, f64::max),
+            Length::Clamp(min, value, max) => value
+                .evaluate(env)
+                .min(max.evaluate(env))
+                .max(min.evaluate(env)),
+        }
+    }
+}
+
+/// Whether a math expression is a number or a length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Number,
+    Length,
+}
+
+/// How deeply a math expression can nest, counting parentheses, math functions
+/// and operators, which each add a level to the expression tree. Deeper
+/// expressions are invalid, so that untrusted input can't overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A recursive descent parser for lengths and math expressions.
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    /// The depth of the expression being parsed.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    /// Skips whitespace, returning whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let len = rest.len() - rest.trim_start_matches(is_css_whitespace).len();
+        self.position += len;
+        len > 0
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matches = self.rest().starts_with(c);
+        if matches {
+            self.position += c.len_utf8();
+        }
+        matches
+    }
+
+    /// Consumes a CSS `<number>`, e.g. `-1.5` or `1e3`.
+    fn number(&mut self) -> Option<f64> {
+        let bytes = self.rest().as_bytes();
+        let digits = |from: usize| {
+            bytes[from.min(bytes.len())..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+        };
+
+        let mut len = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+        let integer = digits(len);
+        len += integer;
+        if bytes.get(len) == Some(&b'.') && digits(len + 1) > 0 {
+            len += 1 + digits(len + 1);
+        } else if integer == 0 {
+            return None;
+        }
+        if matches!(bytes.get(len), Some(b'e' | b'E')) {
+            let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+            let exponent = digits(len + 1 + sign);
+            if exponent > 0 {
+                len += 1 + sign + exponent;
+            }
+        }
+
+        let value = self.rest()[..len].parse().ok()?;
+        self.position += len;
+        Some(value)
+    }
+
+    /// Consumes a function name and its opening parenthesis.
+    fn function_name(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(rest.len());
+        if len == 0 || !rest[len..].starts_with('(') {
+            return None;
+        }
+        self.position += len + 1;
+        Some(rest[..len].to_ascii_lowercase())
+    }
+
+    /// Goes one level deeper into the expression, failing past [`MAX_DEPTH`].
+    fn descend(&mut self) -> Result<(), LengthError> {
+        if self.depth == MAX_DEPTH {
+            return Err(LengthError::Invalid);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Parses a number, a dimension, a parenthesized expression or a math function.
+    fn value(&mut self) -> Result<(Length, Type), LengthError> {
+        if self.eat('(') {
+            self.descend()?;
+            let value = self.sum()?;
+            self.close()?;
+            self.depth -= 1;
+            return Ok(value);
+        }
+        if let Some(name) = self.function_name() {
+            self.descend()?;
+            let value = self.function(&name)?;
+            self.depth -= 1;
+            return Ok(value);
+        }
+
+        let value = self.number().ok_or(LengthError::Invalid)?;
+        if self.eat('%') {
+            return Err(LengthError::Percentage);
+        }
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Ok((Length::Number(value), Type::Number));
+        }
+
+        let unit = rest[..len].to_ascii_lowercase();
+        if !UNITS.iter().any(|(u, ..)| *u == unit) {
+            return Err(LengthError::UnknownUnit);
+        }
+        self.position += len;
+        Ok((Length::Dimension { value, unit }, Type::Length))
+    }
+
+    /// Consumes a closing parenthesis, after optional whitespace.
+    fn close(&mut self) -> Result<(), LengthError> {
+        self.skip_whitespace();
+        self.eat(')').then_some(()).ok_or(LengthError::Invalid)
+    }
+
+    /// Parses the arguments of a math function, after its opening parenthesis.
+    fn function(&mut self, name: &str) -> Result<(Length, Type), LengthError> {
+        let mut args = Vec::new();
+        loop {
+            args.push(self.sum()?);
+            self.skip_whitespace();
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.close()?;
+
+        let ty = args[0].1;
+        if args.iter().any(|&(_, t)| t != ty) {
+            return Err(LengthError::NotALength);
+        }
+        let mut args: Vec<Length> = args.into_iter().map(|(arg, _)| arg).collect();
+
+        let length = match (name, args.len()) {
+            ("calc", 1) => args.remove(0),
+            ("min", _) => Length::Min(args),
+            ("max", _) => Length::Max(args),
+            ("clamp", 3) => {
+                let max = args.pop().map(Box::new);
+                let value = args.pop().map(Box::new);
+                let min = args.pop().map(Box::new);
+                Length::Clamp(
+                    min.ok_or(LengthError::Invalid)?,
+                    value.ok_or(LengthError::Invalid)?,
+                    max.ok_or(LengthError::Invalid)?,
+                )
+            }
+            _ => return Err(LengthError::Invalid),
+        };
+        Ok((length, ty))
+    }
+
+    /// Parses `<calc-sum>`. `+` and `-` must be surrounded by whitespace.
+    fn sum(&mut self) -> Result<(Length, Type), LengthError> {
+        self.skip_whitespace();
+        let depth = self.depth;
+        let (mut sum, ty) = self.product()?;
+
+        loop {
+            let start = self.position;
+            let spaced = self.skip_whitespace();
+            let operator = self.rest().chars().next();
+            if !spaced || !matches!(operator, Some('+' | '-')) {
+                self.position = start;
+                break;
+            }
+            self.position += 1;
+            if !self.skip_whitespace() {
+                return Err(LengthError::Invalid);
+            }
+
+            self.descend()?;
+            let (operand, operand_ty) = self.product()?;
+            if operand_ty != ty {
+                return Err(LengthError::NotALength);
+            }
+            let (a, b) = (Box::new(sum), Box::new(operand));
+            sum = match operator {
+                Some('+') => Length::Sum(a, b),
+                _ => Length::Difference(a, b),
+            };
+        }
+        self.depth = depth;
+        Ok((sum, ty))
+    }
+
+    /// Parses `<calc-product>`. At least one factor must be a number, and
+    /// divisors must be numbers.
+    fn product(&mut self) -> Result<(Length, Type), LengthError> {
+        let depth = self.depth;
+        let (mut product, mut ty) = self.value()?;
+
+        loop {
+            let start = self.position;
+            self.skip_whitespace();
+            let operator = self.rest().chars().next();
+            if !matches!(operator, Some('*' | '/')) {
+                self.position = start;
+                break;
+            }
+            self.position += 1;
+            self.skip_whitespace();
+
+            self.descend()?;
+            let (operand, operand_ty) = self.value()?;
+            let (a, b) = (Box::new(product), Box::new(operand));
+            (product, ty) = match (operator, ty, operand_ty) {
+                (Some('*'), Type::Number, _) => (Length::Product(a, b), operand_ty),
+                (Some('*'), _, Type::Number) => (Length::Product(a, b), ty),
+                (_, _, Type::Number) => (Length::Quotient(a, b), ty),
+                _ => return Err(LengthError::NotALength),
+            };
+        }
+        self.depth = depth;
+        Ok((product, ty))
+    }
+}
+
+/// Parses a CSS `<length>`: a dimension, a unitless zero, or a `calc()`, `min()`,
+/// `max()` or `clamp()` that evaluates to a length.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::length::parse_length;
+/// use srcset_parse::media::MediaEnvironment;
+/// use srcset_parse::LengthError;
+///
+/// let env = MediaEnvironment::new(1024.0, 768.0, 1.0).with_root_font_size(20.0);
+/// assert_eq!(parse_length("calc(100vw - 2rem)").unwrap().evaluate(&env), 984.0);
+/// assert_eq!(parse_length("50%"), Err(LengthError::Percentage));
+/// assert_eq!(parse_length("calc(2 * 3)"), Err(LengthError::NotALength));
+/// ```
+pub fn parse_length(input: &str) -> Result<Length, LengthError> {
+    let input = input.trim_matches(is_css_whitespace);
+    let mut parser = Parser {
+        input,
+        position: 0,
+        depth: 0,
+    };
+
+    let is_function = parser.function_name().is_some();
+    parser.position = 0;
+    if !is_function && input.starts_with('(') {
+        return Err(LengthError::Invalid);
+    }
+
+    let (length, ty) = parser.value()?;
+    if !parser.rest().is_empty() {
+        return Err(LengthError::Invalid);
+    }
+
+    match (length, ty) {
+        (length, Type::Length) => Ok(length),
+        // Zero is the only length that can be written without a unit.
+        (Length::Number(value), _) if value == 0.0 && !is_function => Ok(Length::Dimension {
+            value,
+            unit: "px".to_string(),
+        }),
+        _ => Err(LengthError::NotALength),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::parse_length;
+    use crate::media::MediaEnvironment;
+    use crate::LengthError;
+
+    fn px(length: &str) -> f64 {
+        let env = MediaEnvironment::new(800.0, 600.0, 1.0);
+        parse_length(length)
+            .unwrap_or_else(|e| panic!("{length:?}: {e}"))
+            .evaluate(&env)
+    }
+
+    #[test]
+    fn evaluates_dimensions() {
+        assert_eq!(px("640px"), 640.0);
+        assert_eq!(px("50VW"), 400.0);
+        assert_eq!(px("50vh"), 300.0);
+        assert_eq!(px("10vmin"), 60.0);
+        assert_eq!(px("10vmax"), 80.0);
+        assert_eq!(px("2em"), 32.0);
+        assert_eq!(px("2rem"), 32.0);
+        assert_eq!(px("10ch"), 80.0);
+        assert_eq!(px("1in"), 96.0);
+        assert_eq!(px(" +1.5e1px "), 15.0);
+        assert_eq!(px("0"), 0.0);
+        assert_eq!(px("-10px"), -10.0);
+    }
+
+    #[test]
+    fn uses_the_root_font_size() {
+        let env = MediaEnvironment::new(800.0, 600.0, 1.0).with_root_font_size(10.0);
+        assert_eq!(parse_length("3rem").unwrap().evaluate(&env), 30.0);
+        assert_eq!(parse_length("3em").unwrap().evaluate(&env), 30.0);
+    }
+
+    #[test]
+    fn evaluates_math_functions() {
+        assert_eq!(px("calc(100vw - 2rem)"), 768.0);
+        assert_eq!(px("CALC(100vw/4 + 10px * 2)"), 220.0);
+        assert_eq!(px("calc((100vw - 200px) / 2)"), 300.0);
+        assert_eq!(px("calc(2 * (1px + 1px))"), 4.0);
+        assert_eq!(px("min(50vw, 640px)"), 400.0);
+        assert_eq!(px("max(50vw, 640px, 10em)"), 640.0);
+        assert_eq!(px("clamp(100px, 50vw, 300px)"), 300.0);
+        assert_eq!(px("clamp(500px, 50vw, 300px)"), 500.0);
+        assert_eq!(px("calc(min(10px, 5px) * 3)"), 15.0);
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        let error = |length| parse_length(length).unwrap_err();
+        assert_eq!(error("50%"), LengthError::Percentage);
+        assert_eq!(error("calc(100vw - 10%)"), LengthError::Percentage);
+        assert_eq!(error("10deg"), LengthError::UnknownUnit);
+        assert_eq!(error("10"), LengthError::NotALength);
+        assert_eq!(error("calc(0)"), LengthError::NotALength);
+        assert_eq!(error("calc(1px + 2)"), LengthError::NotALength);
+        assert_eq!(error("calc(1px * 1px)"), LengthError::NotALength);
+        assert_eq!(error("calc(2 / 1px)"), LengthError::NotALength);
+        assert_eq!(error("calc(100vw -2rem)"), LengthError::Invalid);
+        assert_eq!(error("calc(100vw-2rem)"), LengthError::Invalid);
+        assert_eq!(error("calc(1px"), LengthError::Invalid);
+        assert_eq!(error("clamp(1px, 2px)"), LengthError::Invalid);
+        assert_eq!(error("var(--x)"), LengthError::Invalid);
+        assert_eq!(error("(1px)"), LengthError::Invalid);
+        assert_eq!(error(""), LengthError::Invalid);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_expressions() {
+        let error = |length: &str| parse_length(length).unwrap_err();
+        let nested = |depth| format!("{}1px{}", "calc(".repeat(depth), ")".repeat(depth));
+        assert_eq!(px(&nested(50)), 1.0);
+        assert_eq!(error(&nested(5000)), LengthError::Invalid);
+        assert_eq!(
+            error(&format!(
+                "{}1px{}",
+                "calc((".repeat(2500),
+                "))".repeat(2500)
+            )),
+            LengthError::Invalid
+        );
+
+        let sum = |terms| format!("calc(1px{})", " + 1px".repeat(terms));
+        assert_eq!(px(&sum(50)), 51.0);
+        assert_eq!(error(&sum(200_000)), LengthError::Invalid);
+        assert_eq!(
+            error(&format!("calc(1px{})", " * 2".repeat(200_000))),
+            LengthError::Invalid
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Lexing helpers shared by the `srcset`, `sizes`, media query, length and
+//! `image-set()` parsers.
+
+use alloc::vec::Vec;
+
+/// CSS whitespace: SPACE, TAB, LF, CR and FF.
+pub(crate) fn is_css_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+/// Whether `c` can appear in a CSS identifier, escapes aside.
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii()
+}
+
+/// Whether `s` is one or more ASCII digits.
+pub(crate) fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether `s` is a decimal number without a sign, e.g. `1`, `.5` or `1.5e-3`.
+/// This is the grammar of both HTML's floating-point numbers and CSS
+/// `<number>`s, which differ only in the signs they allow in front.
+pub(crate) fn is_unsigned_number(s: &str) -> bool {
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let mantissa_valid = match mantissa.split_once('.') {
+        Some((int, frac)) => (int.is_empty() || is_digits(int)) && is_digits(frac),
+        None => is_digits(mantissa),
+    };
+    let exponent_valid =
+        exponent.is_none_or(|e| is_digits(e.strip_prefix(['-', '+']).unwrap_or(e)));
+
+    mantissa_valid && exponent_valid
+}
+
+/// Whether `s` is a non-negative CSS `<number>`, e.g. `1`, `+.5` or `1e3`.
+pub(crate) fn is_non_negative_number(s: &str) -> bool {
+    is_unsigned_number(s.strip_prefix('+').unwrap_or(s))
+}
+
+/// Splits `input` on the commas that are not nested in a block or a string.
+pub(crate) fn split_top_level_commas(input: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 => {
+                parts.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+
+    parts
+}
@@ -1,36 +1,117 @@
-use regex::Regex;
-use std::sync::OnceLock;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use segment::Segment;
+
+mod builder;
+mod descriptor;
+mod error;
+pub mod html;
+mod image_set;
+pub mod length;
+mod lex;
+pub mod media;
+mod normalize;
+mod order;
+pub mod picture;
+mod rewrite;
+mod segment;
+mod select;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod serialize;
+pub mod sizes;
+mod spec;
+mod srcset;
+mod trace;
+mod url;
+
+pub use builder::SrcsetBuilder;
+pub use descriptor::{parse_candidates, Candidate, Descriptor};
+pub use error::{
+    BuildError, Diagnostic, ErrorKind, ImageSetError, LengthError, ParseError, SerializeError,
+    UrlError,
+};
+pub use image_set::{parse_image_set, ImageSetOption};
+pub use normalize::{normalize, DropReason, DroppedCandidate};
+pub use order::{
+    sort_by_density, sort_by_effective_density, sort_by_width, Density, Height, Width,
+};
+pub use rewrite::rewrite_urls;
+pub use select::{select, select_with_trace, SelectionContext, SelectionStrategy};
+pub use serialize::to_srcset_string;
+pub use spec::{parse_spec, parse_spec_iter, parse_with_diagnostics, try_parse};
+pub use srcset::Srcset;
+pub use trace::{CandidateOutcome, CandidateTrace, Decision, SelectionTrace, SourceSizeOrigin};
+pub use url::{resolve_all, resolve_url, ResolvedCandidate};
 
 /// A single candidate in a `srcset`: a URL plus optional "width" or "density".
+///
+/// A candidate with a width may also carry a "height" (the `h` descriptor), which
+/// gives the intrinsic aspect ratio of the image.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct ImageCandidate {
     pub url: String,
     pub width: Option<f64>,
     pub density: Option<f64>,
+    pub height: Option<f64>,
 }
 
-impl PartialOrd for ImageCandidate {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match (self.width, self.density, other.width, other.density) {
-            (Some(a), None, Some(b), None) => Some(a.partial_cmp(&b).unwrap()),
-            (None, Some(a), None, Some(b)) => Some(a.partial_cmp(&b).unwrap()),
-            _ => None,
+/// An [`ImageCandidate`] whose URL borrows from the parsed `srcset` string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct BorrowedCandidate<'a> {
+    pub url: &'a str,
+    pub width: Option<f64>,
+    pub density: Option<f64>,
+    pub height: Option<f64>,
+}
+
+impl BorrowedCandidate<'_> {
+    /// Copies the URL into an owned [`ImageCandidate`].
+    pub fn into_owned(self) -> ImageCandidate {
+        ImageCandidate {
+            url: self.url.to_string(),
+            width: self.width,
+            density: self.density,
+            height: self.height,
         }
     }
 }
 
-/// Regex for matching srcset segments.
-///
-/// Explanation:
-/// 1. `(\S*[^,\s])` captures a run of non-whitespace, stopping before `,` or space at the end,
-///    which we treat as the `url`.
-/// 2. `(\s+([\d.]+)(x|w))?` is optional (`?`) and captures:
-///    - `([\d.]+)` which is the numeric part (value),
-///    - `(x|w)` which indicates the descriptor (density or width).
-///
-/// The entire pattern is repeated globally on the input text.
-static SRCSEG_PATTERN: &str = r"(\S*[^,\s])(\s+([\d.]+)(x|w))?";
-static SRCSEG_REGEX: OnceLock<Regex> = OnceLock::new();
+impl<'a> From<BorrowedCandidate<'a>> for ImageCandidate {
+    fn from(candidate: BorrowedCandidate<'a>) -> Self {
+        candidate.into_owned()
+    }
+}
+
+/// Compares two candidates with the same kind of descriptor, by descriptor value,
+/// then height, then URL, so that only equal candidates compare as equal.
+/// Candidates with different kinds of descriptors, or with `NaN` values, are not
+/// comparable; see [`ImageCandidate::total_cmp`] for a total order.
+impl PartialOrd for ImageCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        use core::cmp::Ordering;
+
+        let ordering = match (self.width, self.density, other.width, other.density) {
+            (Some(a), None, Some(b), None) => a.partial_cmp(&b)?,
+            (None, Some(a), None, Some(b)) => a.partial_cmp(&b)?,
+            _ => return None,
+        };
+        if ordering != Ordering::Equal {
+            return Some(ordering);
+        }
+        match self.height.partial_cmp(&other.height)? {
+            Ordering::Equal => Some(self.url.cmp(&other.url)),
+            ordering => Some(ordering),
+        }
+    }
+}
 
 /// Parses an `srcset` string and returns a vector of `ImageCandidate`s.
 ///
@@ -44,45 +125,59 @@ static SRCSEG_REGEX: OnceLock<Regex> = OnceLock::new();
 /// assert_eq!(candidates[2].width, Some(100.0));
 /// ```
 pub fn parse(srcset: &str) -> Vec<ImageCandidate> {
-    let re = SRCSEG_REGEX.get_or_init(|| Regex::new(SRCSEG_PATTERN).expect("Invalid regex"));
-    let mut results = Vec::new();
-
-    for caps in re.captures_iter(srcset) {
-        // Group 1: the `url`
-        let url = caps
-            .get(1)
-            .map(|m| m.as_str().to_string())
-            .unwrap_or_default();
-
-        // Group 3: the numeric value (e.g. "1", "2", "100")
-        let value = caps.get(3).map(|m| m.as_str());
-        // Group 4: the descriptor (e.g. "x" or "w")
-        let descriptor = caps.get(4).map(|m| m.as_str());
-
-        // Convert the captured numeric value to f64 if present
-        let parsed_value = value.map(|v| v.parse::<f64>().unwrap_or_default());
-
-        // Fill in the struct's fields based on the descriptor
-        let (width, density) = match descriptor {
-            Some("w") => (parsed_value, None),
-            Some("x") => (None, parsed_value),
-            _ => (None, None),
-        };
+    parse_iter(srcset).collect()
+}
 
-        results.push(ImageCandidate {
-            url,
-            width,
-            density,
-        });
-    }
+/// Parses an `srcset` string like [`parse`], yielding the candidates lazily as the
+/// input is matched.
+///
+/// # Examples
+/// ```
+/// let srcset = "image1.png 1x, image2.png 2x, image3.png 100w";
+/// let widest = srcset_parse::parse_iter(srcset)
+///     .filter_map(|c| c.width)
+///     .fold(None, |max: Option<f64>, w| Some(max.map_or(w, |m| m.max(w))));
+/// assert_eq!(widest, Some(100.0));
+/// ```
+pub fn parse_iter(srcset: &str) -> impl Iterator<Item = ImageCandidate> + '_ {
+    parse_borrowed_iter(srcset).map(BorrowedCandidate::into_owned)
+}
 
-    results
+/// Parses an `srcset` string like [`parse`], borrowing the URLs from the input
+/// instead of allocating a `String` for each of them.
+///
+/// # Examples
+/// ```
+/// let srcset = String::from("image1.png 1x, image2.png 100w");
+/// let candidates = srcset_parse::parse_borrowed(&srcset);
+/// assert_eq!(candidates[0].url, "image1.png");
+/// assert_eq!(candidates[1].width, Some(100.0));
+///
+/// let owned = candidates[0].into_owned();
+/// assert_eq!(owned.url, "image1.png");
+/// ```
+pub fn parse_borrowed(srcset: &str) -> Vec<BorrowedCandidate<'_>> {
+    parse_borrowed_iter(srcset).collect()
+}
+
+fn parse_borrowed_iter(srcset: &str) -> impl Iterator<Item = BorrowedCandidate<'_>> {
+    segment::segments(srcset).map(Segment::into_candidate)
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::{parse, ImageCandidate};
+    use super::{parse, parse_borrowed, parse_iter, BorrowedCandidate, ImageCandidate};
+
+    /// Builds a candidate without a height, including ones `parse` can't produce.
+    pub(crate) fn candidate(url: &str, width: Option<f64>, density: Option<f64>) -> ImageCandidate {
+        ImageCandidate {
+            url: url.to_string(),
+            width,
+            density,
+            height: None,
+        }
+    }
 
     #[test]
     fn parses_srcset_strings() {
@@ -95,11 +190,13 @@ mod tests {
                     url: "cat-@2x.jpeg".to_string(),
                     width: None,
                     density: Some(2.0),
+                    height: None,
                 },
                 ImageCandidate {
                     url: "dog.jpeg".to_string(),
                     width: Some(100.0),
                     density: None,
+                    height: None,
                 },
             ]
         );
@@ -120,11 +217,13 @@ mod tests {
                     url: "foo-bar.png".to_string(),
                     width: None,
                     density: Some(2.0),
+                    height: None,
                 },
                 ImageCandidate {
                     url: "bar-baz.png".to_string(),
                     width: Some(100.0),
                     density: None,
+                    height: None,
                 },
             ]
         );
@@ -141,11 +240,13 @@ mod tests {
                     url: "cat.jpeg".to_string(),
                     width: None,
                     density: Some(2.4),
+                    height: None,
                 },
                 ImageCandidate {
                     url: "dog.jpeg".to_string(),
                     width: None,
                     density: Some(1.5),
+                    height: None,
                 },
             ]
         );
@@ -166,11 +267,13 @@ mod tests {
                     url: "https://foo.bar/w=100,h=200/dog.png".to_string(),
                     width: Some(100.0),
                     density: None,
+                    height: None,
                 },
                 ImageCandidate {
                     url: "https://baz.bar/cat.png?meow=yes".to_string(),
                     width: Some(1024.0),
                     density: None,
+                    height: None,
                 },
             ]
         );
@@ -186,6 +289,7 @@ mod tests {
                 url: "/cat.jpg".to_string(),
                 width: None,
                 density: None,
+                height: None,
             }]
         );
     }
@@ -201,18 +305,111 @@ mod tests {
                     url: "/cat.jpg".to_string(),
                     width: None,
                     density: None,
+                    height: None,
                 },
                 ImageCandidate {
                     url: "/dog.png".to_string(),
                     width: None,
                     density: Some(3.0),
+                    height: None,
                 },
                 ImageCandidate {
                     url: "/lol".to_string(),
                     width: None,
                     density: None,
+                    height: None,
                 },
             ]
         );
     }
+
+    #[test]
+    fn supports_height_descriptors() {
+        let srcset = "img.jpg 400w 300h, other.jpg 800w";
+        let result = parse(srcset);
+        assert_eq!(
+            result,
+            vec![
+                ImageCandidate {
+                    url: "img.jpg".to_string(),
+                    width: Some(400.0),
+                    density: None,
+                    height: Some(300.0),
+                },
+                ImageCandidate {
+                    url: "other.jpg".to_string(),
+                    width: Some(800.0),
+                    density: None,
+                    height: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn borrows_urls_from_the_input() {
+        let srcset = String::from("cat.jpeg 2x, dog.jpeg 100w");
+        let result = parse_borrowed(&srcset);
+        assert_eq!(
+            result,
+            vec![
+                BorrowedCandidate {
+                    url: "cat.jpeg",
+                    width: None,
+                    density: Some(2.0),
+                    height: None,
+                },
+                BorrowedCandidate {
+                    url: "dog.jpeg",
+                    width: Some(100.0),
+                    density: None,
+                    height: None,
+                },
+            ]
+        );
+        assert!(srcset
+            .as_bytes()
+            .as_ptr_range()
+            .contains(&result[1].url.as_ptr()));
+        assert_eq!(
+            result
+                .into_iter()
+                .map(ImageCandidate::from)
+                .collect::<Vec<_>>(),
+            parse(&srcset)
+        );
+    }
+
+    #[test]
+    fn parses_lazily() {
+        let srcset = "a.png 1x, b.png 2x, c.png 3x";
+        let mut iter = parse_iter(srcset);
+        assert_eq!(iter.next().map(|c| c.url), Some("a.png".to_string()));
+        assert_eq!(iter.collect::<Vec<_>>(), parse(srcset)[1..]);
+    }
+
+    #[test]
+    fn orders_consistently_with_equality() {
+        use core::cmp::Ordering;
+
+        let candidates = parse("a.png 100w, b.png 100w, a.png 100w 50h, a.png 200w, a.png 2x");
+        assert_eq!(
+            candidates[0].partial_cmp(&candidates[1]),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            candidates[0].partial_cmp(&candidates[2]),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            candidates[1].partial_cmp(&candidates[3]),
+            Some(Ordering::Less)
+        );
+        assert_eq!(candidates[0].partial_cmp(&candidates[4]), None);
+        for a in &candidates {
+            for b in &candidates {
+                assert_eq!(a == b, a.partial_cmp(b) == Some(Ordering::Equal));
+            }
+        }
+    }
 }
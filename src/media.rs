@@ -0,0 +1,777 @@
+//! Parsing and evaluation of [media queries](https://drafts.csswg.org/mediaqueries-4/),
+//! as found in `sizes` and `<source media>`.
+//!
+//! Conditions combine features with `and`, `or` and `not`, and features can use the
+//! range syntax:
+//!
+//! ```
+//! use srcset_parse::media::{parse_media_condition, MediaEnvironment};
+//!
+//! let condition = parse_media_condition("(400px <= width <= 700px) and (orientation: portrait)")
+//!     .unwrap();
+//! assert!(condition.matches(&MediaEnvironment::new(600.0, 800.0, 2.0)));
+//! assert!(!condition.matches(&MediaEnvironment::new(800.0, 600.0, 2.0)));
+//! ```
+//!
+//! The supported features are `width` and `height` (with their `min-` and `max-`
+//! forms), `orientation`, `resolution` (likewise), `prefers-color-scheme` and
+//! `prefers-reduced-data`. Other features and unknown syntax evaluate to "unknown",
+//! which is treated as false, as in browsers.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::image_set::parse_resolution;
+use crate::length::parse_length;
+use crate::lex::{is_css_whitespace, is_ident_char, split_top_level_commas};
+
+/// A user's preferred color scheme, for `prefers-color-scheme`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// The font size of the root element in browsers' default style sheets, in CSS
+/// pixels.
+const DEFAULT_FONT_SIZE: f64 = 16.0;
+
+/// How deeply conditions can be nested in parentheses. Deeper conditions are
+/// invalid, so that untrusted input can't overflow the stack.
+const MAX_NESTING: usize = 64;
+
+/// The environment media queries are evaluated in.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaEnvironment {
+    /// The viewport width, in CSS pixels.
+    pub viewport_width: f64,
+    /// The viewport height, in CSS pixels.
+    pub viewport_height: f64,
+    /// The number of device pixels per CSS pixel.
+    pub device_pixel_ratio: f64,
+    pub color_scheme: ColorScheme,
+    /// Whether the user asked for less data to be used, for `prefers-reduced-data`.
+    pub prefers_reduced_data: bool,
+    /// The font size `em` and `rem` resolve against, in CSS pixels.
+    pub root_font_size: f64,
+}
+
+impl MediaEnvironment {
+    /// Creates a screen environment with a light color scheme, no preference for
+    /// reduced data and a 16px root font size.
+    pub fn new(viewport_width: f64, viewport_height: f64, device_pixel_ratio: f64) -> Self {
+        Self {
+            viewport_width,
+            viewport_height,
+            device_pixel_ratio,
+            color_scheme: ColorScheme::Light,
+            prefers_reduced_data: false,
+            root_font_size: DEFAULT_FONT_SIZE,
+        }
+    }
+
+    pub fn with_color_scheme(mut self, color_scheme: ColorScheme) -> Self {
+        self.color_scheme = color_scheme;
+        self
+    }
+
+    pub fn with_reduced_data(mut self, prefers_reduced_data: bool) -> Self {
+        self.prefers_reduced_data = prefers_reduced_data;
+        self
+    }
+
+    pub fn with_root_font_size(mut self, root_font_size: f64) -> Self {
+        self.root_font_size = root_font_size;
+        self
+    }
+}
+
+/// A comparison in a media feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    /// The comparison with its operands swapped, e.g. `>` for `<`.
+    fn flip(self) -> Self {
+        match self {
+            Comparison::Lt => Comparison::Gt,
+            Comparison::Le => Comparison::Ge,
+            Comparison::Eq => Comparison::Eq,
+            Comparison::Ge => Comparison::Le,
+            Comparison::Gt => Comparison::Lt,
+        }
+    }
+
+    fn compare(self, actual: f64, expected: f64) -> bool {
+        match self {
+            Comparison::Lt => actual < expected,
+            Comparison::Le => actual <= expected,
+            Comparison::Eq => actual == expected,
+            Comparison::Ge => actual >= expected,
+            Comparison::Gt => actual > expected,
+        }
+    }
+}
+
+/// A media feature, e.g. `(min-width: 600px)` or `(400px < width <= 700px)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaFeature {
+    /// The feature name, in lowercase and without any `min-` or `max-` prefix.
+    pub name: String,
+    /// How the feature's value compares to each of the given values, as written,
+    /// with the feature on the left: `(min-width: 600px)` is `width >= 600px`. Empty
+    /// when the feature is used on its own, like `(orientation)`.
+    pub comparisons: Vec<(Comparison, String)>,
+}
+
+/// A media condition.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Condition {
+    Feature(MediaFeature),
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    /// Syntax reserved for future extensions, such as a function or an unknown
+    /// expression in parentheses. It always evaluates to "unknown".
+    Unknown,
+}
+
+/// A media query, e.g. `not print and (min-width: 600px)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaQuery {
+    /// Whether the query starts with `not`.
+    pub negated: bool,
+    /// The media type, in lowercase, if any.
+    pub media_type: Option<String>,
+    pub condition: Option<Condition>,
+}
+
+/// A comma-separated list of media queries, as found in `<source media>`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaQueryList {
+    /// The queries. An invalid query is replaced by `not all`, which never matches.
+    pub queries: Vec<MediaQuery>,
+}
+
+/// The result of evaluating a condition: `None` means "unknown".
+type Kleene = Option<bool>;
+
+fn kleene_not(value: Kleene) -> Kleene {
+    value.map(|value| !value)
+}
+
+fn kleene_and(values: impl Iterator<Item = Kleene>) -> Kleene {
+    let mut result = Some(true);
+    for value in values {
+        match value {
+            Some(false) => return Some(false),
+            None => result = None,
+            Some(true) => {}
+        }
+    }
+    result
+}
+
+fn kleene_or(values: impl Iterator<Item = Kleene>) -> Kleene {
+    let mut result = Some(false);
+    for value in values {
+        match value {
+            Some(true) => return Some(true),
+            None => result = None,
+            Some(false) => {}
+        }
+    }
+    result
+}
+
+impl MediaFeature {
+    /// Evaluates a feature with a numeric value, like `width`.
+    fn evaluate_range(&self, actual: f64, parse: impl Fn(&str) -> Option<f64>) -> Kleene {
+        if self.comparisons.is_empty() {
+            return Some(actual != 0.0);
+        }
+        kleene_and(self.comparisons.iter().map(|(comparison, value)| {
+            parse(value).map(|expected| comparison.compare(actual, expected))
+        }))
+    }
+
+    /// Evaluates a feature with keyword values, like `orientation`. `active` is
+    /// whether the feature is true on its own, e.g. `(prefers-reduced-data)`.
+    fn evaluate_discrete(&self, actual: &str, values: &[&str], active: bool) -> Kleene {
+        match self.comparisons.as_slice() {
+            [] => Some(active),
+            [(Comparison::Eq, value)] if values.contains(&value.as_str()) => Some(value == actual),
+            _ => None,
+        }
+    }
+
+    fn evaluate(&self, env: &MediaEnvironment) -> Kleene {
+        let length = |value: &str| parse_length(value).ok().map(|l| l.evaluate(env));
+
+        match self.name.as_str() {
+            "width" => self.evaluate_range(env.viewport_width, length),
+            "height" => self.evaluate_range(env.viewport_height, length),
+            "resolution" => self.evaluate_range(env.device_pixel_ratio, parse_resolution),
+            "orientation" => {
+                let orientation = if env.viewport_height >= env.viewport_width {
+                    "portrait"
+                } else {
+                    "landscape"
+                };
+                self.evaluate_discrete(orientation, &["portrait", "landscape"], true)
+            }
+            "prefers-color-scheme" => {
+                let scheme = match env.color_scheme {
+                    ColorScheme::Light => "light",
+                    ColorScheme::Dark => "dark",
+                };
+                self.evaluate_discrete(scheme, &["light", "dark"], true)
+            }
+            "prefers-reduced-data" => {
+                let preference = if env.prefers_reduced_data {
+                    "reduce"
+                } else {
+                    "no-preference"
+                };
+                let values = ["no-preference", "reduce"];
+                self.evaluate_discrete(preference, &values, env.prefers_reduced_data)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Condition {
+    fn evaluate(&self, env: &MediaEnvironment) -> Kleene {
+        match self {
+            Condition::Feature(feature) => feature.evaluate(env),
+            Condition::Not(condition) => kleene_not(condition.evaluate(env)),
+            Condition::And(conditions) => kleene_and(conditions.iter().map(|c| c.evaluate(env))),
+            Condition::Or(conditions) => kleene_or(conditions.iter().map(|c| c.evaluate(env))),
+            Condition::Unknown => None,
+        }
+    }
+
+    /// Whether the condition is true in `env`. Unknown features make it false.
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        self.evaluate(env) == Some(true)
+    }
+}
+
+impl MediaQuery {
+    /// The query `not all`, which never matches.
+    fn not_all() -> Self {
+        Self {
+            negated: true,
+            media_type: Some("all".to_string()),
+            condition: None,
+        }
+    }
+
+    /// Whether the query matches in `env`, a screen.
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        let media_type = match self.media_type.as_deref() {
+            None | Some("all" | "screen") => Some(true),
+            Some(_) => Some(false),
+        };
+        let condition = self
+            .condition
+            .as_ref()
+            .map_or(Some(true), |c| c.evaluate(env));
+        let result = kleene_and([media_type, condition].into_iter());
+
+        let result = if self.negated {
+            kleene_not(result)
+        } else {
+            result
+        };
+        result == Some(true)
+    }
+}
+
+impl MediaQueryList {
+    /// Whether any query matches in `env`. An empty list always matches.
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|query| query.matches(env))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    /// A function, with its arguments, e.g. `calc(100vw - 2em)`.
+    Function(String),
+    /// A number, with its unit if any, e.g. `600px`.
+    Number(String),
+    Colon,
+    Open,
+    Close,
+    Comparison(Comparison),
+    Delim(char),
+}
+
+/// Splits lowercased input into tokens, skipping whitespace.
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        let next = rest[c.len_utf8()..].chars().next();
+        let starts_number = |c: char| c.is_ascii_digit() || c == '.';
+
+        let (token, len) = if is_css_whitespace(c) {
+            (None, 1)
+        } else if starts_number(c) || (matches!(c, '+' | '-') && next.is_some_and(starts_number)) {
+            let mut len = c.len_utf8();
+            let mut previous = c;
+            for c in rest[len..].chars() {
+                let exponent_sign = matches!(c, '+' | '-') && previous == 'e';
+                if !(c.is_ascii_alphanumeric() || c == '.' || c == '%' || exponent_sign) {
+                    break;
+                }
+                len += 1;
+                previous = c;
+            }
+            (Some(Token::Number(rest[..len].to_string())), len)
+        } else if is_ident_char(c) && !c.is_ascii_digit() {
+            let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            if rest[len..].starts_with('(') {
+                let mut depth = 0;
+                let end = rest[len..]
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                        depth == 0
+                    })
+                    .map_or(rest.len(), |(i, _)| len + i + 1);
+                (Some(Token::Function(rest[..end].to_string())), end)
+            } else {
+                (Some(Token::Ident(rest[..len].to_string())), len)
+            }
+        } else {
+            match (c, next) {
+                ('<', Some('=')) => (Some(Token::Comparison(Comparison::Le)), 2),
+                ('>', Some('=')) => (Some(Token::Comparison(Comparison::Ge)), 2),
+                ('<', _) => (Some(Token::Comparison(Comparison::Lt)), 1),
+                ('>', _) => (Some(Token::Comparison(Comparison::Gt)), 1),
+                ('=', _) => (Some(Token::Comparison(Comparison::Eq)), 1),
+                ('(', _) => (Some(Token::Open), 1),
+                (')', _) => (Some(Token::Close), 1),
+                (':', _) => (Some(Token::Colon), 1),
+                (c, _) => (Some(Token::Delim(c)), c.len_utf8()),
+            }
+        };
+
+        tokens.extend(token);
+        rest = &rest[len..];
+    }
+
+    tokens
+}
+
+/// A recursive descent parser for the media query grammar.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// How many conditions are being parsed inside each other.
+    depth: usize,
+    /// Whether a condition was nested more than [`MAX_NESTING`] deep.
+    too_deep: bool,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self {
+            tokens: tokenize(&input.to_ascii_lowercase()),
+            position: 0,
+            depth: 0,
+            too_deep: false,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(i)) if i == ident)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self.peek() == Some(token);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        let matches = self.peek_ident(ident);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn at_end(&self) -> bool {
+        self.position == self.tokens.len()
+    }
+
+    /// Parses `<media-condition>`, or `<media-condition-without-or>` if `allow_or`
+    /// is false.
+    fn condition(&mut self, allow_or: bool) -> Option<Condition> {
+        if self.eat_ident("not") {
+            return Some(Condition::Not(Box::new(self.in_parens()?)));
+        }
+
+        let mut conditions = vec![self.in_parens()?];
+        let mut combinator = None;
+        while let Some(Token::Ident(ident)) = self.peek() {
+            let ident = ident.clone();
+            if !(ident == "and" || ident == "or" && allow_or) {
+                break;
+            }
+            // `and` and `or` can't be mixed without parentheses.
+            if combinator.get_or_insert_with(|| ident.clone()) != &ident {
+                return None;
+            }
+            self.position += 1;
+            conditions.push(self.in_parens()?);
+        }
+
+        Some(match combinator.as_deref() {
+            None => conditions.pop()?,
+            Some("and") => Condition::And(conditions),
+            _ => Condition::Or(conditions),
+        })
+    }
+
+    /// Parses `<media-in-parens>`, falling back to `<general-enclosed>`.
+    fn in_parens(&mut self) -> Option<Condition> {
+        match self.peek()? {
+            Token::Function(_) => {
+                self.position += 1;
+                return Some(Condition::Unknown);
+            }
+            Token::Open => self.position += 1,
+            _ => return None,
+        }
+        let start = self.position;
+
+        if matches!(self.peek(), Some(Token::Open)) || self.peek_ident("not") {
+            if self.depth == MAX_NESTING {
+                self.too_deep = true;
+            }
+            if self.too_deep {
+                return None;
+            }
+            self.depth += 1;
+            let condition = self.condition(true);
+            self.depth -= 1;
+            if self.too_deep {
+                return None;
+            }
+            if let Some(condition) = condition {
+                if self.eat(&Token::Close) {
+                    return Some(condition);
+                }
+            }
+            self.position = start;
+        }
+
+        if let Some(feature) = self.feature() {
+            if self.eat(&Token::Close) {
+                return Some(Condition::Feature(feature));
+            }
+        }
+        self.position = start;
+
+        // Skip to the matching parenthesis. An unclosed one makes the condition
+        // invalid, since whatever follows it, like a `sizes` length, would be inside.
+        let mut depth = 0usize;
+        while let Some(token) = self.tokens.get(self.position) {
+            self.position += 1;
+            match token {
+                Token::Open => depth += 1,
+                Token::Close if depth == 0 => return Some(Condition::Unknown),
+                Token::Close => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Parses the contents of `<media-feature>`, up to the closing parenthesis.
+    fn feature(&mut self) -> Option<MediaFeature> {
+        let end = self.tokens[self.position..]
+            .iter()
+            .position(|t| matches!(t, Token::Open | Token::Close))
+            .map_or(self.tokens.len(), |i| self.position + i);
+        let is_value = |t: &Token| matches!(t, Token::Number(_) | Token::Function(_));
+        let value = |t: &Token| match t {
+            Token::Ident(s) | Token::Number(s) | Token::Function(s) => Some(s.clone()),
+            _ => None,
+        };
+        let range_name = |name: &str| {
+            (!name.starts_with("min-") && !name.starts_with("max-")).then(|| name.to_string())
+        };
+
+        let (name, comparisons) = match &self.tokens[self.position..end] {
+            [Token::Ident(name)] => (range_name(name)?, Vec::new()),
+            [Token::Ident(name), Token::Colon, v] => {
+                let v = value(v)?;
+                match (name.strip_prefix("min-"), name.strip_prefix("max-")) {
+                    (Some(name), _) => (name.to_string(), vec![(Comparison::Ge, v)]),
+                    (_, Some(name)) => (name.to_string(), vec![(Comparison::Le, v)]),
+                    _ => (name.clone(), vec![(Comparison::Eq, v)]),
+                }
+            }
+            [Token::Ident(name), Token::Comparison(op), v] if is_value(v) => {
+                (range_name(name)?, vec![(*op, value(v)?)])
+            }
+            [v, Token::Comparison(op), Token::Ident(name)] if is_value(v) => {
+                (range_name(name)?, vec![(op.flip(), value(v)?)])
+            }
+            [low, Token::Comparison(op1), Token::Ident(name), Token::Comparison(op2), high]
+                if is_value(low) && is_value(high) =>
+            {
+                let less = |op| matches!(op, Comparison::Lt | Comparison::Le);
+                let greater = |op| matches!(op, Comparison::Gt | Comparison::Ge);
+                if !(less(*op1) && less(*op2) || greater(*op1) && greater(*op2)) {
+                    return None;
+                }
+                let comparisons = vec![(op1.flip(), value(low)?), (*op2, value(high)?)];
+                (range_name(name)?, comparisons)
+            }
+            _ => return None,
+        };
+
+        self.position = end;
+        Some(MediaFeature { name, comparisons })
+    }
+
+    /// Parses `<media-query>`.
+    fn query(&mut self) -> Option<MediaQuery> {
+        let starts_with_condition = match self.tokens.get(self.position..self.position + 2) {
+            Some([Token::Ident(not), Token::Open | Token::Function(_)]) => not == "not",
+            _ => matches!(self.peek(), Some(Token::Open | Token::Function(_))),
+        };
+        if starts_with_condition {
+            let condition = self.condition(true)?;
+            return Some(MediaQuery {
+                negated: false,
+                media_type: None,
+                condition: Some(condition),
+            });
+        }
+
+        let negated = self.eat_ident("not");
+        if !negated {
+            self.eat_ident("only");
+        }
+        let media_type = match self.peek() {
+            Some(Token::Ident(t)) if !matches!(t.as_str(), "not" | "and" | "or" | "only") => {
+                t.clone()
+            }
+            _ => return None,
+        };
+        self.position += 1;
+
+        let condition = if self.eat_ident("and") {
+            Some(self.condition(false)?)
+        } else {
+            None
+        };
+        Some(MediaQuery {
+            negated,
+            media_type: Some(media_type),
+            condition,
+        })
+    }
+}
+
+/// Parses a `<media-condition>`, as found in `sizes`. Returns `None` if the input
+/// doesn't follow the grammar; syntax that does but isn't understood, such as an
+/// unknown function, parses to [`Condition::Unknown`].
+pub fn parse_media_condition(condition: &str) -> Option<Condition> {
+    let mut parser = Parser::new(condition);
+    let condition = parser.condition(true)?;
+    parser.at_end().then_some(condition)
+}
+
+/// Parses a media query list, as found in `<source media>`.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::media::{parse_media_query_list, ColorScheme, MediaEnvironment};
+///
+/// let list = parse_media_query_list("print, screen and (prefers-color-scheme: dark)");
+/// let env = MediaEnvironment::new(1024.0, 768.0, 1.0);
+/// assert!(!list.matches(&env));
+/// assert!(list.matches(&env.with_color_scheme(ColorScheme::Dark)));
+/// ```
+pub fn parse_media_query_list(list: &str) -> MediaQueryList {
+    if list.trim_matches(is_css_whitespace).is_empty() {
+        return MediaQueryList::default();
+    }
+
+    let queries = split_top_level_commas(list)
+        .into_iter()
+        .map(|query| {
+            let mut parser = Parser::new(query);
+            parser
+                .query()
+                .filter(|_| parser.at_end())
+                .unwrap_or_else(MediaQuery::not_all)
+        })
+        .collect();
+    MediaQueryList { queries }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{
+        parse_media_condition, parse_media_query_list, ColorScheme, Comparison, Condition,
+        MediaEnvironment, MediaFeature,
+    };
+
+    fn env() -> MediaEnvironment {
+        MediaEnvironment::new(600.0, 800.0, 2.0)
+    }
+
+    fn matches(condition: &str, env: &MediaEnvironment) -> bool {
+        parse_media_condition(condition)
+            .unwrap_or_else(|| panic!("invalid condition {condition:?}"))
+            .matches(env)
+    }
+
+    #[test]
+    fn parses_features() {
+        assert_eq!(
+            parse_media_condition("(MIN-WIDTH: 600px)"),
+            Some(Condition::Feature(MediaFeature {
+                name: "width".to_string(),
+                comparisons: vec![(Comparison::Ge, "600px".to_string())],
+            }))
+        );
+        assert_eq!(
+            parse_media_condition("(400px < width <= 700px)"),
+            Some(Condition::Feature(MediaFeature {
+                name: "width".to_string(),
+                comparisons: vec![
+                    (Comparison::Gt, "400px".to_string()),
+                    (Comparison::Le, "700px".to_string()),
+                ],
+            }))
+        );
+    }
+
+    #[test]
+    fn evaluates_widths_and_heights() {
+        let env = env();
+        assert!(matches("(min-width: 600px)", &env));
+        assert!(!matches("(max-width: 599.5px)", &env));
+        assert!(matches("(width >= 37.5em)", &env));
+        assert!(matches("(1000px > width)", &env));
+        assert!(matches("(400px <= width <= 700px)", &env));
+        assert!(!matches("(700px >= width > 600px)", &env));
+        assert!(matches("(height: 100vh)", &env));
+        assert!(matches("(width)", &env));
+    }
+
+    #[test]
+    fn evaluates_other_features() {
+        let env = env();
+        assert!(matches("(orientation: portrait)", &env));
+        assert!(matches("(min-resolution: 192dpi)", &env));
+        assert!(!matches("(resolution > 2x)", &env));
+        assert!(matches("(prefers-color-scheme: light)", &env));
+        assert!(!matches("(prefers-reduced-data)", &env));
+
+        let env = env
+            .with_color_scheme(ColorScheme::Dark)
+            .with_reduced_data(true);
+        assert!(matches("(prefers-color-scheme: dark)", &env));
+        assert!(matches("(prefers-reduced-data: reduce)", &env));
+    }
+
+    #[test]
+    fn combines_conditions() {
+        let env = env();
+        assert!(matches("(min-width: 500px) and (max-width: 700px)", &env));
+        assert!(matches(
+            "(max-width: 500px) or (orientation: portrait)",
+            &env
+        ));
+        assert!(matches("not (max-width: 500px)", &env));
+        assert!(matches("((width > 1px) and (not (width < 1px)))", &env));
+
+        assert_eq!(parse_media_condition("(a) and (b) or (c)"), None);
+        assert_eq!(parse_media_condition("not (a) and (b)"), None);
+        assert_eq!(parse_media_condition("(width: 1px"), None);
+        assert_eq!(
+            parse_media_condition("(width: 1px 2px)"),
+            Some(Condition::Unknown)
+        );
+        assert_eq!(parse_media_condition("screen"), None);
+    }
+
+    #[test]
+    fn treats_unknown_as_false() {
+        let env = env();
+        assert!(!matches("(unknown-feature)", &env));
+        assert!(!matches("not (unknown-feature)", &env));
+        assert!(!matches("(width: 16/9)", &env));
+        assert!(!matches("(min-orientation: portrait)", &env));
+        assert!(matches("(unknown-feature) or (width)", &env));
+        assert!(!matches("(width) and foo(bar)", &env));
+    }
+
+    #[test]
+    fn evaluates_query_lists() {
+        let env = env();
+        let list = |list: &str| parse_media_query_list(list).matches(&env);
+        assert!(list(""));
+        assert!(list("screen"));
+        assert!(list("ONLY screen and (min-width: 600px)"));
+        assert!(!list("print"));
+        assert!(list("not print"));
+        assert!(list("not print and (max-width: 100px)"));
+        assert!(!list("screen and (max-width: 100px) or (width)"));
+        assert!(list("invalid query!, (width)"));
+        assert!(!list("invalid query!"));
+        assert!(!list("not (width)"));
+    }
+
+    #[test]
+    fn rejects_deeply_nested_conditions() {
+        let nested = |depth| format!("{}(width){}", "(".repeat(depth), ")".repeat(depth));
+        assert!(matches(&nested(50), &env()));
+        assert_eq!(parse_media_condition(&nested(5000)), None);
+        assert_eq!(parse_media_condition(&"not (".repeat(5000)), None);
+        assert!(!parse_media_query_list(&nested(5000)).matches(&env()));
+        let sizes = crate::sizes::parse_sizes(&format!("{} 10px", nested(5000)));
+        assert!(sizes.entries.is_empty());
+    }
+}
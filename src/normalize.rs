@@ -0,0 +1,137 @@
+//! The post-processing browsers apply to a parsed `srcset` before selecting from it.
+
+use alloc::vec::Vec;
+
+use crate::{ImageCandidate, Srcset};
+
+/// Why [`normalize`] dropped a candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DropReason {
+    /// The candidate has the same density as the candidate at index `first`.
+    DuplicateDensity { first: usize },
+    /// The candidate has the same width as the candidate at index `first`, so it
+    /// would have the same density whatever the source size.
+    DuplicateWidth { first: usize },
+    /// The candidate has both a width and a density.
+    ConflictingDescriptors,
+}
+
+/// A candidate dropped by [`normalize`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DroppedCandidate {
+    /// The index the candidate had before normalization.
+    pub index: usize,
+    pub candidate: ImageCandidate,
+    pub reason: DropReason,
+}
+
+/// Applies the spec's post-processing to parsed candidates: a candidate without
+/// descriptors becomes `1x`, and a candidate with the same density or width as an
+/// earlier one is dropped. Returns the dropped candidates, in order.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::{normalize, DropReason};
+///
+/// let mut candidates = srcset_parse::parse("a.png, b.png 1x, c.png 2x");
+/// let dropped = normalize(&mut candidates);
+///
+/// assert_eq!(candidates.len(), 2);
+/// assert_eq!(candidates[0].density, Some(1.0));
+/// assert_eq!(dropped[0].candidate.url, "b.png");
+/// assert_eq!(dropped[0].reason, DropReason::DuplicateDensity { first: 0 });
+/// ```
+pub fn normalize(candidates: &mut Vec<ImageCandidate>) -> Vec<DroppedCandidate> {
+    // The kept candidates, with their original indices.
+    let mut kept: Vec<(usize, ImageCandidate)> = Vec::with_capacity(candidates.len());
+    let mut dropped = Vec::new();
+
+    for (index, mut candidate) in candidates.drain(..).enumerate() {
+        let reason = match (candidate.width, candidate.density) {
+            (Some(_), Some(_)) => Some(DropReason::ConflictingDescriptors),
+            (Some(width), None) => kept
+                .iter()
+                .find(|(_, c)| c.width == Some(width))
+                .map(|&(first, _)| DropReason::DuplicateWidth { first }),
+            (None, density) => {
+                let density = density.unwrap_or(1.0);
+                candidate.density = Some(density);
+                kept.iter()
+                    .find(|(_, c)| c.density == Some(density))
+                    .map(|&(first, _)| DropReason::DuplicateDensity { first })
+            }
+        };
+
+        match reason {
+            Some(reason) => dropped.push(DroppedCandidate {
+                index,
+                candidate,
+                reason,
+            }),
+            None => kept.push((index, candidate)),
+        }
+    }
+
+    candidates.extend(kept.into_iter().map(|(_, candidate)| candidate));
+    dropped
+}
+
+impl Srcset {
+    /// Applies the spec's post-processing to the candidates. See [`normalize`].
+    pub fn normalize(&mut self) -> Vec<DroppedCandidate> {
+        let mut candidates = core::mem::take(self).into_candidates();
+        let dropped = normalize(&mut candidates);
+        *self = Srcset::new(candidates);
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{normalize, DropReason, DroppedCandidate};
+    use crate::{parse, ImageCandidate, Srcset};
+
+    #[test]
+    fn drops_duplicates_and_reports_why() {
+        let mut srcset = Srcset::new(parse("a.png 100w, b.png 2x, c.png 100w, d.png, e.png 2x"));
+        let dropped = srcset.normalize();
+
+        let kept: Vec<_> = srcset.iter().map(|c| (c.url.as_str(), c.density)).collect();
+        assert_eq!(
+            kept,
+            vec![("a.png", None), ("b.png", Some(2.0)), ("d.png", Some(1.0))]
+        );
+
+        let reasons: Vec<_> = dropped.iter().map(|d| (d.index, d.reason)).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (2, DropReason::DuplicateWidth { first: 0 }),
+                (4, DropReason::DuplicateDensity { first: 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_conflicting_descriptors() {
+        let conflicting = ImageCandidate {
+            url: "a.png".to_string(),
+            width: Some(100.0),
+            density: Some(1.0),
+            height: None,
+        };
+        let mut candidates = vec![conflicting.clone()];
+        assert_eq!(
+            normalize(&mut candidates),
+            vec![DroppedCandidate {
+                index: 0,
+                candidate: conflicting,
+                reason: DropReason::ConflictingDescriptors,
+            }]
+        );
+        assert!(candidates.is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! Panic-free ordering of image candidates.
+//!
+//! All comparisons use [`f64::total_cmp`], so candidates with `NaN` descriptors sort
+//! deterministically instead of panicking.
+
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+use crate::ImageCandidate;
+
+macro_rules! total_order_newtype {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
+        pub struct $name(pub f64);
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_bits().hash(state);
+            }
+        }
+    };
+}
+
+total_order_newtype!(
+    /// A `w` descriptor value, ordered with [`f64::total_cmp`].
+    Width
+);
+
+total_order_newtype!(
+    /// An `h` descriptor value, ordered with [`f64::total_cmp`].
+    Height
+);
+
+total_order_newtype!(
+    /// An `x` descriptor value, ordered with [`f64::total_cmp`].
+    Density
+);
+
+impl ImageCandidate {
+    /// The candidate's width, as an [`Ord`] value.
+    pub fn width_key(&self) -> Option<Width> {
+        self.width.map(Width)
+    }
+
+    /// The candidate's height, as an [`Ord`] value.
+    pub fn height_key(&self) -> Option<Height> {
+        self.height.map(Height)
+    }
+
+    /// The candidate's density, as an [`Ord`] value. A candidate without
+    /// descriptors counts as `1x`.
+    pub fn density_key(&self) -> Option<Density> {
+        match (self.width, self.density) {
+            (None, None) => Some(Density(1.0)),
+            (None, Some(density)) => Some(Density(density)),
+            (Some(_), _) => None,
+        }
+    }
+
+    /// A total order over candidates, which never panics.
+    ///
+    /// Candidates are ordered by descriptor kind (none, then density, then width,
+    /// then both), then by descriptor values, then by URL.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        let kind = |c: &Self| match (c.width, c.density) {
+            (None, None) => 0,
+            (None, Some(_)) => 1,
+            (Some(_), None) => 2,
+            (Some(_), Some(_)) => 3,
+        };
+
+        kind(self)
+            .cmp(&kind(other))
+            .then_with(|| self.width.map(Width).cmp(&other.width.map(Width)))
+            .then_with(|| self.density.map(Density).cmp(&other.density.map(Density)))
+            .then_with(|| self.height_key().cmp(&other.height_key()))
+            .then_with(|| self.url.cmp(&other.url))
+    }
+}
+
+/// Sorts candidates by ascending `key`, with `None` keys last. The sort is stable.
+fn sort_by_optional_key<K: Ord>(
+    candidates: &mut [ImageCandidate],
+    key: impl Fn(&ImageCandidate) -> Option<K>,
+) {
+    candidates.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+/// Sorts candidates by ascending width. Candidates without a width go last, in
+/// their original order.
+///
+/// # Examples
+/// ```
+/// let mut candidates = srcset_parse::parse("b.png 800w, c.png 2x, a.png 400w");
+/// srcset_parse::sort_by_width(&mut candidates);
+/// let urls: Vec<_> = candidates.iter().map(|c| c.url.as_str()).collect();
+/// assert_eq!(urls, vec!["a.png", "b.png", "c.png"]);
+/// ```
+pub fn sort_by_width(candidates: &mut [ImageCandidate]) {
+    sort_by_optional_key(candidates, ImageCandidate::width_key);
+}
+
+/// Sorts candidates by ascending density, counting candidates without descriptors
+/// as `1x`. Candidates with a width go last, in their original order.
+pub fn sort_by_density(candidates: &mut [ImageCandidate]) {
+    sort_by_optional_key(candidates, ImageCandidate::density_key);
+}
+
+/// Sorts candidates by ascending density when rendered `source_size` CSS pixels
+/// wide, so that `w` and `x` descriptors can be compared. Candidates without an
+/// effective density go last, in their original order.
+///
+/// # Examples
+/// ```
+/// let mut candidates = srcset_parse::parse("a.png 800w, b.png 1.5x, c.png 400w");
+/// srcset_parse::sort_by_effective_density(&mut candidates, 400.0);
+/// let urls: Vec<_> = candidates.iter().map(|c| c.url.as_str()).collect();
+/// assert_eq!(urls, vec!["c.png", "b.png", "a.png"]);
+/// ```
+pub fn sort_by_effective_density(candidates: &mut [ImageCandidate], source_size: f64) {
+    sort_by_optional_key(candidates, |c| {
+        c.effective_density(source_size).map(Density)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{sort_by_density, sort_by_width, Density, Height, Width};
+    use crate::tests::candidate;
+    use crate::{parse, ImageCandidate};
+
+    fn urls(candidates: &[ImageCandidate]) -> Vec<&str> {
+        candidates.iter().map(|c| c.url.as_str()).collect()
+    }
+
+    #[test]
+    fn comparing_nan_descriptors_does_not_panic() {
+        let nan = candidate("nan.png", None, Some(f64::NAN));
+        let two = candidate("two.png", None, Some(2.0));
+        assert_eq!(nan.partial_cmp(&two), None);
+
+        let mut candidates = vec![nan, two, candidate("none.png", None, None)];
+        sort_by_density(&mut candidates);
+        assert_eq!(urls(&candidates), vec!["none.png", "two.png", "nan.png"]);
+
+        candidates.sort_by(ImageCandidate::total_cmp);
+        assert_eq!(urls(&candidates), vec!["none.png", "two.png", "nan.png"]);
+    }
+
+    #[test]
+    fn newtypes_are_totally_ordered() {
+        let mut widths = [Width(f64::NAN), Width(2.0), Width(-0.0), Width(0.0)];
+        widths.sort();
+        assert_eq!(widths[..3], [Width(-0.0), Width(0.0), Width(2.0)]);
+        assert_eq!(Density(f64::NAN), Density(f64::NAN));
+        assert!(Density(1.0) < Density(1.5));
+
+        let tall = ImageCandidate {
+            height: Some(200.0),
+            ..candidate("a.png", Some(100.0), None)
+        };
+        assert_eq!(tall.height_key(), Some(Height(200.0)));
+    }
+
+    #[test]
+    fn sort_by_width_is_stable() {
+        let mut candidates = parse("a 2x, b 300w, c, d 100w");
+        sort_by_width(&mut candidates);
+        assert_eq!(urls(&candidates), vec!["d", "b", "a", "c"]);
+    }
+}
@@ -0,0 +1,423 @@
+//! Source selection for `<picture>` elements, following the HTML standard's
+//! ["update the source set"](https://html.spec.whatwg.org/multipage/images.html#update-the-source-set)
+//! algorithm.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::media::{parse_media_query_list, MediaEnvironment};
+use crate::sizes::{parse_sizes, SourceSizes};
+use crate::{
+    parse, select, select_with_trace, ImageCandidate, SelectionContext, SelectionStrategy,
+    SelectionTrace, SourceSizeOrigin,
+};
+
+/// The image formats most browsers can decode.
+const COMMON_IMAGE_TYPES: &[&str] = &[
+    "image/avif",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/svg+xml",
+    "image/webp",
+];
+
+/// The environment a `<picture>` is rendered in.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Environment {
+    /// The environment `media` and `sizes` are evaluated in.
+    pub media: MediaEnvironment,
+    /// The MIME types the browser can decode, e.g. `image/webp`.
+    pub supported_types: Vec<String>,
+    /// The browser behavior to model when picking a candidate.
+    pub strategy: SelectionStrategy,
+}
+
+impl Environment {
+    /// Creates an environment supporting the common image formats: AVIF, GIF, JPEG,
+    /// PNG, SVG and WebP.
+    pub fn new(viewport_width: f64, viewport_height: f64, device_pixel_ratio: f64) -> Self {
+        Self::from(MediaEnvironment::new(
+            viewport_width,
+            viewport_height,
+            device_pixel_ratio,
+        ))
+    }
+
+    pub fn with_supported_types<I, S>(mut self, types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.supported_types = types.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: SelectionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Whether `mime_type` is supported, ignoring parameters like `codecs`.
+    pub fn supports_type(&self, mime_type: &str) -> bool {
+        let essence = mime_type.split(';').next().unwrap_or_default().trim();
+        self.supported_types
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(essence))
+    }
+}
+
+/// An environment for `media`, supporting the common image formats.
+impl From<MediaEnvironment> for Environment {
+    fn from(media: MediaEnvironment) -> Self {
+        Self {
+            media,
+            supported_types: COMMON_IMAGE_TYPES.iter().map(|t| t.to_string()).collect(),
+            strategy: SelectionStrategy::Spec,
+        }
+    }
+}
+
+/// A `<source>` element.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Source {
+    pub srcset: Vec<ImageCandidate>,
+    pub sizes: Option<SourceSizes>,
+    /// The `media` attribute: a media query list.
+    pub media: Option<String>,
+    /// The `type` attribute: a MIME type.
+    pub mime_type: Option<String>,
+}
+
+impl Source {
+    pub fn new(srcset: &str) -> Self {
+        Self {
+            srcset: parse(srcset),
+            sizes: None,
+            media: None,
+            mime_type: None,
+        }
+    }
+
+    pub fn with_sizes(mut self, sizes: &str) -> Self {
+        self.sizes = Some(parse_sizes(sizes));
+        self
+    }
+
+    pub fn with_media(mut self, media: impl Into<String>) -> Self {
+        self.media = Some(media.into());
+        self
+    }
+
+    pub fn with_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
+/// The fallback `<img>` element of a `<picture>`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Img {
+    pub src: Option<String>,
+    pub srcset: Vec<ImageCandidate>,
+    pub sizes: Option<SourceSizes>,
+    /// Whether the image is lazy-loaded, i.e. has `loading="lazy"`.
+    pub lazy: bool,
+    /// The width the image is laid out at, in CSS pixels, if it is rendered. A
+    /// `sizes` list starting with `auto` resolves to it when the image is lazy.
+    pub layout_width: Option<f64>,
+}
+
+impl Img {
+    pub fn new(src: impl Into<String>) -> Self {
+        Self {
+            src: Some(src.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_srcset(mut self, srcset: &str) -> Self {
+        self.srcset = parse(srcset);
+        self
+    }
+
+    pub fn with_sizes(mut self, sizes: &str) -> Self {
+        self.sizes = Some(parse_sizes(sizes));
+        self
+    }
+
+    pub fn with_lazy_loading(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    pub fn with_layout_width(mut self, layout_width: f64) -> Self {
+        self.layout_width = Some(layout_width);
+        self
+    }
+
+    /// The candidates of the `<img>`: its `srcset`, plus its `src` as a `1x`
+    /// candidate unless that would be redundant.
+    fn source_set(&self) -> Vec<ImageCandidate> {
+        let mut candidates = self.srcset.clone();
+        let redundant = candidates
+            .iter()
+            .any(|c| c.width.is_some() || c.density == Some(1.0));
+
+        match &self.src {
+            Some(src) if !src.is_empty() && !redundant => candidates.push(ImageCandidate {
+                url: src.clone(),
+                width: None,
+                density: Some(1.0),
+                height: None,
+            }),
+            _ => {}
+        }
+
+        candidates
+    }
+}
+
+/// A `<picture>` element: its `<source>` children, in order, and its `<img>`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Picture {
+    pub sources: Vec<Source>,
+    pub img: Img,
+}
+
+/// The result of [`Picture::select`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PictureSelection<'a> {
+    /// The `<source>` that was used, or `None` if the `<img>` was.
+    pub source: Option<&'a Source>,
+    /// The candidate a browser would fetch.
+    pub candidate: ImageCandidate,
+}
+
+/// The element a [`Picture`] selects from, and how to select.
+struct Chosen<'a> {
+    source: Option<&'a Source>,
+    candidates: Cow<'a, [ImageCandidate]>,
+    context: SelectionContext,
+    origin: SourceSizeOrigin,
+}
+
+impl Picture {
+    pub fn new(img: Img) -> Self {
+        Self {
+            sources: Vec::new(),
+            img,
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Selects the source and candidate a browser would use in `env`.
+    ///
+    /// The first `<source>` with candidates whose `media` matches and whose `type` is
+    /// supported wins; otherwise the `<img>` is used. Returns `None` if the chosen
+    /// element has no usable candidate.
+    ///
+    /// # Examples
+    /// ```
+    /// use srcset_parse::picture::{Environment, Img, Picture, Source};
+    ///
+    /// let picture = Picture::new(Img::new("cat.jpg"))
+    ///     .with_source(Source::new("cat.avif").with_type("image/avif"))
+    ///     .with_source(Source::new("cat.webp 1x, cat@2x.webp 2x").with_type("image/webp"));
+    ///
+    /// let env = Environment::new(375.0, 812.0, 3.0).with_supported_types(["image/webp"]);
+    /// let selection = picture.select(&env).unwrap();
+    /// assert_eq!(selection.candidate.url, "cat@2x.webp");
+    /// ```
+    pub fn select(&self, env: &Environment) -> Option<PictureSelection<'_>> {
+        let chosen = self.choose(env);
+        let candidate = select(&chosen.candidates, &chosen.context)?;
+        Some(PictureSelection {
+            source: chosen.source,
+            candidate: candidate.clone(),
+        })
+    }
+
+    /// Selects the source and candidate like [`Picture::select`], also returning a
+    /// [`SelectionTrace`] that records the `media` and `sizes` condition that
+    /// matched and every step of the candidate selection. The trace holds a copy
+    /// of every candidate, so prefer [`Picture::select`] when it isn't needed.
+    ///
+    /// # Examples
+    /// ```
+    /// use srcset_parse::picture::{Environment, Img, Picture, Source};
+    /// use srcset_parse::SourceSizeOrigin;
+    ///
+    /// let picture = Picture::new(Img::new("cat.jpg")).with_source(
+    ///     Source::new("cat-800.jpg 800w, cat-2400.jpg 2400w")
+    ///         .with_media("(orientation: portrait)")
+    ///         .with_sizes("(max-width: 600px) 100vw, 50vw"),
+    /// );
+    ///
+    /// let (selection, trace) = picture.select_with_trace(&Environment::new(390.0, 844.0, 3.0));
+    /// assert_eq!(selection.unwrap().candidate.url, "cat-2400.jpg");
+    /// assert_eq!(trace.source_media.as_deref(), Some("(orientation: portrait)"));
+    /// assert_eq!(
+    ///     trace.source_size_origin,
+    ///     SourceSizeOrigin::Condition("(max-width: 600px)".to_string())
+    /// );
+    /// ```
+    pub fn select_with_trace(
+        &self,
+        env: &Environment,
+    ) -> (Option<PictureSelection<'_>>, SelectionTrace) {
+        let chosen = self.choose(env);
+        let (candidate, mut trace) = select_with_trace(&chosen.candidates, &chosen.context);
+        trace.source_media = chosen.source.and_then(|s| s.media.clone());
+        trace.source_size_origin = chosen.origin;
+
+        let selection = candidate.map(|candidate| PictureSelection {
+            source: chosen.source,
+            candidate: candidate.clone(),
+        });
+        (selection, trace)
+    }
+
+    /// Chooses the `<source>` or `<img>` to select from in `env`, and resolves
+    /// its source size.
+    fn choose(&self, env: &Environment) -> Chosen<'_> {
+        let source = self.sources.iter().find(|source| {
+            !source.srcset.is_empty()
+                && source
+                    .media
+                    .as_deref()
+                    .is_none_or(|m| parse_media_query_list(m).matches(&env.media))
+                && source
+                    .mime_type
+                    .as_deref()
+                    .is_none_or(|t| env.supports_type(t))
+        });
+
+        let (candidates, sizes) = match source {
+            Some(source) => (Cow::Borrowed(&source.srcset[..]), source.sizes.as_ref()),
+            None => (Cow::Owned(self.img.source_set()), self.img.sizes.as_ref()),
+        };
+
+        let media = &env.media;
+        let mut context = SelectionContext::new(media.viewport_width, media.device_pixel_ratio)
+            .with_strategy(env.strategy);
+        let mut origin = SourceSizeOrigin::Viewport;
+        if let Some(sizes) = sizes {
+            // `auto` applies to the `<source>` sizes too, but only when the
+            // `<img>` is lazy-loaded.
+            let layout_width = self.img.layout_width.filter(|_| self.img.lazy);
+            let (source_size, sizes_origin) = sizes.resolve(media, layout_width);
+            context = context.with_source_size(source_size);
+            origin = sizes_origin;
+        }
+
+        Chosen {
+            source,
+            candidates,
+            context,
+            origin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Environment, Img, Picture, Source};
+
+    fn picture() -> Picture {
+        Picture::new(Img::new("fallback.jpg").with_srcset("fallback@2x.jpg 2x"))
+            .with_source(
+                Source::new("wide-1x.jpg 1x, wide-2x.jpg 2x").with_media("(min-width: 800px)"),
+            )
+            .with_source(
+                Source::new("narrow-400.webp 400w, narrow-800.webp 800w")
+                    .with_sizes("(max-width: 400px) 100vw, 50vw")
+                    .with_type("image/webp; codecs=vp8"),
+            )
+    }
+
+    fn selected(picture: &Picture, env: &Environment) -> (Option<usize>, String) {
+        let selection = picture.select(env).unwrap();
+        assert_eq!(picture.select_with_trace(env).0.as_ref(), Some(&selection));
+        let index = selection
+            .source
+            .map(|s| picture.sources.iter().position(|p| p == s).unwrap());
+        (index, selection.candidate.url)
+    }
+
+    #[test]
+    fn uses_the_first_matching_source() {
+        let picture = picture();
+        assert_eq!(
+            selected(&picture, &Environment::new(1024.0, 768.0, 2.0)),
+            (Some(0), "wide-2x.jpg".to_string())
+        );
+        assert_eq!(
+            selected(&picture, &Environment::new(400.0, 800.0, 1.0)),
+            (Some(1), "narrow-400.webp".to_string())
+        );
+        assert_eq!(
+            selected(&picture, &Environment::new(600.0, 800.0, 2.0)),
+            (Some(1), "narrow-800.webp".to_string())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_img() {
+        let picture = picture();
+        let env = Environment::new(400.0, 800.0, 1.0).with_supported_types(["image/jpeg"]);
+        assert_eq!(selected(&picture, &env), (None, "fallback.jpg".to_string()));
+
+        let env = env.with_supported_types(Vec::<String>::new());
+        let mut env = env;
+        env.media.device_pixel_ratio = 3.0;
+        assert_eq!(
+            selected(&picture, &env),
+            (None, "fallback@2x.jpg".to_string())
+        );
+    }
+
+    #[test]
+    fn uses_the_layout_width_for_lazy_images() {
+        let img = Img::new("fallback.jpg")
+            .with_srcset("small.jpg 300w, large.jpg 1000w")
+            .with_sizes("auto, 100vw")
+            .with_layout_width(250.0);
+        let env = Environment::new(800.0, 600.0, 1.0);
+
+        // Without `loading="lazy"`, `auto` is ignored.
+        let picture = Picture::new(img.clone());
+        assert_eq!(selected(&picture, &env), (None, "large.jpg".to_string()));
+
+        let picture = Picture::new(img.clone().with_lazy_loading());
+        assert_eq!(selected(&picture, &env), (None, "small.jpg".to_string()));
+
+        // A lazy image that isn't laid out falls back too.
+        let picture = Picture::new(Img {
+            layout_width: None,
+            ..img.with_lazy_loading()
+        });
+        assert_eq!(selected(&picture, &env), (None, "large.jpg".to_string()));
+    }
+
+    #[test]
+    fn skips_sources_for_other_media_types() {
+        let picture = Picture::new(Img::new("screen.jpg"))
+            .with_source(Source::new("print.jpg").with_media("print"));
+        assert_eq!(
+            selected(&picture, &Environment::new(400.0, 800.0, 1.0)),
+            (None, "screen.jpg".to_string())
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Rewriting the URLs of a `srcset` in place.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use crate::serialize::write_url;
+use crate::spec::Tokenizer;
+
+/// Replaces every candidate URL in `srcset` with `f(url)`, leaving everything else,
+/// including whitespace, commas and descriptors, exactly as written.
+///
+/// URLs are found with the HTML standard's algorithm, like [`crate::parse_spec`],
+/// but candidates with invalid descriptors are rewritten too. Whitespace and
+/// leading or trailing commas in the new URLs are percent-encoded, so that they
+/// don't change how the `srcset` is split. An empty new URL would leave its
+/// descriptors without a URL, so the original URL is kept instead.
+///
+/// # Examples
+/// ```
+/// use std::borrow::Cow;
+///
+/// let srcset = "/img/cat.jpg 1x,\n    /img/cat@2x.jpg   2x";
+/// let rewritten = srcset_parse::rewrite_urls(srcset, |url| match url.strip_prefix("/img/") {
+///     Some(path) => Cow::Owned(format!("https://cdn.example.com/{path}")),
+///     None => Cow::Borrowed(url),
+/// });
+/// assert_eq!(
+///     rewritten,
+///     "https://cdn.example.com/cat.jpg 1x,\n    https://cdn.example.com/cat@2x.jpg   2x"
+/// );
+/// ```
+pub fn rewrite_urls(srcset: &str, mut f: impl FnMut(&str) -> Cow<str>) -> String {
+    let mut rewritten = String::with_capacity(srcset.len());
+    let mut copied = 0;
+
+    for raw in Tokenizer::new(srcset) {
+        let span = raw.url.span();
+        rewritten.push_str(&srcset[copied..span.start]);
+        let url = f(raw.url.text);
+        let url = if url.is_empty() { raw.url.text } else { &url };
+        // Writing to a `String` never fails.
+        let _ = write_url(&mut rewritten, url);
+        copied = span.end;
+    }
+
+    rewritten.push_str(&srcset[copied..]);
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::borrow::Cow;
+
+    use super::rewrite_urls;
+
+    fn upper(srcset: &str) -> String {
+        rewrite_urls(srcset, |url| Cow::Owned(url.to_uppercase()))
+    }
+
+    #[test]
+    fn preserves_layout_and_descriptors() {
+        assert_eq!(
+            upper(" a.png  1.50x ,\tb.png 100W 50h,, c.png, d.png 1x 2x"),
+            " A.PNG  1.50x ,\tB.PNG 100W 50h,, C.PNG, D.PNG 1x 2x"
+        );
+        assert_eq!(upper("a.png,,, b.png,"), "A.PNG,,, B.PNG,");
+        assert_eq!(upper(""), "");
+    }
+
+    #[test]
+    fn keeps_commas_inside_urls() {
+        assert_eq!(
+            upper("data:image/png;base64,iVBO 1x"),
+            "DATA:IMAGE/PNG;BASE64,IVBO 1x"
+        );
+    }
+
+    #[test]
+    fn escapes_urls_that_would_change_the_split() {
+        let rewritten = rewrite_urls("a.png 1x, b.png 2x", |url| {
+            Cow::Owned(format!(",{url} copy"))
+        });
+        assert_eq!(rewritten, "%2Ca.png%20copy 1x, %2Cb.png%20copy 2x");
+    }
+
+    #[test]
+    fn keeps_urls_rewritten_to_nothing() {
+        let rewritten = rewrite_urls("a.png 2x, b.png", |url| match url {
+            "a.png" => Cow::Borrowed(""),
+            _ => Cow::Borrowed("c.png"),
+        });
+        assert_eq!(rewritten, "a.png 2x, c.png");
+    }
+}
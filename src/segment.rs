@@ -0,0 +1,257 @@
+//! The segments [`crate::parse`] splits a `srcset` into.
+//!
+//! With the `std` feature, segments are matched with [`SRCSEG_PATTERN`]. Without
+//! it, [`Segments`] finds exactly the same segments by hand, so that the crate
+//! doesn't need the `regex` crate.
+
+#[cfg(feature = "std")]
+use regex::{Captures, Regex};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+use crate::BorrowedCandidate;
+
+/// Regex for matching srcset segments.
+///
+/// Explanation:
+/// 1. `(\S*[^,\s])` captures a run of non-whitespace, stopping before `,` or space at the end,
+///    which we treat as the `url`.
+/// 2. `(\s+([0-9.]+)(x|w))?` is optional (`?`) and captures:
+///    - `([0-9.]+)` which is the numeric part (value),
+///    - `(x|w)` which indicates the descriptor (density or width),
+///    - `(\s+([0-9.]+)h)?` which is an optional height following it.
+///
+/// The entire pattern is repeated globally on the input text.
+#[cfg(feature = "std")]
+static SRCSEG_PATTERN: &str = r"(\S*[^,\s])(\s+([0-9.]+)(x|w)(\s+([0-9.]+)h)?)?";
+#[cfg(feature = "std")]
+static SRCSEG_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// A segment of a `srcset`: a URL, then optionally a value with an `x` or `w`
+/// descriptor, then optionally a height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Segment<'a> {
+    pub url: &'a str,
+    /// The value and its descriptor, e.g. `("100", 'w')`.
+    pub descriptor: Option<(&'a str, char)>,
+    pub height: Option<&'a str>,
+}
+
+impl<'a> Segment<'a> {
+    #[cfg(feature = "std")]
+    fn from_captures(caps: Captures<'a>) -> Self {
+        let group = |i| caps.get(i).map(|m| m.as_str());
+        Self {
+            // Group 1: the `url`
+            url: group(1).unwrap_or_default(),
+            // Group 3: the numeric value (e.g. "1", "2", "100"), and group 4: the
+            // descriptor (e.g. "x" or "w")
+            descriptor: group(3).zip(group(4).and_then(|d| d.chars().next())),
+            // Group 6: the height value (e.g. "300" in "400w 300h")
+            height: group(6),
+        }
+    }
+
+    pub fn into_candidate(self) -> BorrowedCandidate<'a> {
+        // Convert the captured numeric values to f64 if present
+        let number = |v: &str| v.parse::<f64>().unwrap_or_default();
+        let height = self.height.map(number);
+
+        // Fill in the struct's fields based on the descriptor. A height is only
+        // meaningful alongside a width.
+        let (width, density, height) = match self.descriptor {
+            Some((value, 'w')) => (Some(number(value)), None, height),
+            Some((value, _)) => (None, Some(number(value)), None),
+            None => (None, None, None),
+        };
+
+        BorrowedCandidate {
+            url: self.url,
+            width,
+            density,
+            height,
+        }
+    }
+}
+
+/// Matches `\s+([0-9.]+)(u)` at the start of `input`, where `u` is one of `units`.
+/// Returns the value, the unit and the length of the match.
+#[cfg(any(not(feature = "std"), test))]
+fn match_descriptor<'a>(input: &'a str, units: &[char]) -> Option<(&'a str, char, usize)> {
+    let rest = input.trim_start_matches(char::is_whitespace);
+    if rest.len() == input.len() {
+        return None;
+    }
+
+    let value_len = rest
+        .bytes()
+        .take_while(|&b| b.is_ascii_digit() || b == b'.')
+        .count();
+    let unit = rest[value_len..].chars().next()?;
+    if value_len == 0 || !units.contains(&unit) {
+        return None;
+    }
+
+    let len = input.len() - rest.len() + value_len + unit.len_utf8();
+    Some((&rest[..value_len], unit, len))
+}
+
+/// Finds the segments of a `srcset` without a regex, the way the regex engine
+/// matches [`SRCSEG_PATTERN`] repeatedly: leftmost first, with greedy repetitions.
+#[cfg(any(not(feature = "std"), test))]
+#[derive(Debug, Clone)]
+pub(crate) struct Segments<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+#[cfg(any(not(feature = "std"), test))]
+impl<'a> Segments<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+}
+
+#[cfg(any(not(feature = "std"), test))]
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        loop {
+            let rest = &self.input[self.position..];
+            let start = self.position + rest.find(|c: char| !c.is_whitespace())?;
+            let run = &self.input[start..];
+            let run = &run[..run.find(char::is_whitespace).unwrap_or(run.len())];
+
+            // `\S*[^,\s]` takes the whole run but its trailing commas. A run of
+            // commas doesn't match at all.
+            let url = run.trim_end_matches(',');
+            if url.is_empty() {
+                self.position = start + run.len();
+                continue;
+            }
+
+            let mut segment = Segment {
+                url,
+                descriptor: None,
+                height: None,
+            };
+            self.position = start + url.len();
+
+            // The descriptor must be separated from the URL by whitespace, which
+            // trailing commas would prevent.
+            if url.len() == run.len() {
+                let after_url = &self.input[self.position..];
+                if let Some((value, unit, len)) = match_descriptor(after_url, &['x', 'w']) {
+                    segment.descriptor = Some((value, unit));
+                    self.position += len;
+
+                    let after_descriptor = &self.input[self.position..];
+                    if let Some((height, _, len)) = match_descriptor(after_descriptor, &['h']) {
+                        segment.height = Some(height);
+                        self.position += len;
+                    }
+                }
+            }
+
+            return Some(segment);
+        }
+    }
+}
+
+/// Splits a `srcset` into segments: with [`SRCSEG_PATTERN`] if the `std` feature
+/// is enabled, and with [`Segments`] otherwise.
+pub(crate) fn segments(srcset: &str) -> impl Iterator<Item = Segment<'_>> {
+    #[cfg(feature = "std")]
+    {
+        let re = SRCSEG_REGEX.get_or_init(|| Regex::new(SRCSEG_PATTERN).expect("Invalid regex"));
+        re.captures_iter(srcset).map(Segment::from_captures)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        Segments::new(srcset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Segment, Segments};
+
+    fn segments(srcset: &str) -> Vec<Segment<'_>> {
+        Segments::new(srcset).collect()
+    }
+
+    #[test]
+    fn matches_like_the_pattern() {
+        let found = segments(",a.png,, b.png 1.5x 2h,c 100w\u{3000}50h d 2xy");
+        let expected = [
+            (",a.png", None, None),
+            ("b.png", Some(("1.5", 'x')), Some("2")),
+            (",c", Some(("100", 'w')), Some("50")),
+            ("d", Some(("2", 'x')), None),
+            ("y", None, None),
+        ];
+        let found: Vec<_> = found
+            .iter()
+            .map(|s| (s.url, s.descriptor, s.height))
+            .collect();
+        assert_eq!(found, expected);
+        assert!(segments(" ,, ,").is_empty());
+    }
+
+    #[test]
+    fn only_matches_ascii_digits_in_values() {
+        // `\u{662}` is ARABIC-INDIC DIGIT TWO.
+        let found = segments("a.png \u{662}x");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].descriptor, None);
+        assert_eq!(found[1].url, "\u{662}x");
+    }
+
+    /// Checks that [`Segments`] finds the same segments as the regex on a large
+    /// number of inputs made of the characters that matter to the pattern.
+    #[cfg(feature = "std")]
+    #[test]
+    fn agrees_with_the_regex() {
+        const ALPHABET: &[char] = &[
+            'a', ',', ' ', '\t', '\u{A0}', '1', '0', '.', 'x', 'w', 'h', 'é', '\u{2003}', '\u{662}',
+        ];
+
+        // A small linear congruential generator, so the test is deterministic.
+        let mut state: u64 = 0x5EED;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+
+        let mut inputs: Vec<String> = [
+            "",
+            "a.png",
+            "a.png 1x, b.png 2x",
+            "a.png 100w 50h, b.png 200w",
+            "a.png,b.png 2x",
+            "a.png,, 1x",
+            "data:image/png;base64,iVBO= 1x",
+            "a.png 1x2x 3x",
+            "a.png 1..5x 100w 50h 25h",
+        ]
+        .map(String::from)
+        .to_vec();
+        for _ in 0..20_000 {
+            let len = next() % 16;
+            inputs.push(
+                (0..len)
+                    .map(|_| ALPHABET[next() % ALPHABET.len()])
+                    .collect(),
+            );
+        }
+
+        for input in &inputs {
+            let expected: Vec<_> = super::segments(input).collect();
+            assert_eq!(segments(input), expected, "input: {input:?}");
+        }
+    }
+}
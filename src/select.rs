@@ -0,0 +1,416 @@
+//! Choosing the image candidate a browser would fetch, following the HTML standard's
+//! ["select an image source"](https://html.spec.whatwg.org/multipage/images.html#select-an-image-source)
+//! algorithm.
+
+use alloc::vec::Vec;
+
+use crate::trace::{CandidateOutcome, CandidateTrace, Decision, SelectionTrace, SourceSizeOrigin};
+use crate::ImageCandidate;
+
+/// How a browser picks among the candidates once they are normalized to pixel
+/// densities.
+///
+/// The models cover a first page load: engines also prefer a denser candidate
+/// that is already in their cache, which depends on browsing history and is not
+/// modeled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SelectionStrategy {
+    /// The HTML standard's suggestion: the lowest density that covers the device
+    /// pixel ratio, or the highest density if none does.
+    #[default]
+    Spec,
+    /// Chromium (Chrome, Edge, Opera and other Blink browsers). It takes the
+    /// lowest density covering the device pixel ratio and the density just below
+    /// it, and keeps the lower one unless the ratio reaches their geometric mean,
+    /// so a candidate a little below the ratio beats a much denser one: at `2x`,
+    /// it picks `1.5x` over `3x`, but `4x` over `1x`. When the ratio is 1 or
+    /// less, the covering density always wins.
+    Chromium,
+    /// Firefox (Gecko), which keeps the best candidate seen so far: the lowest
+    /// density that covers the device pixel ratio, or the highest if none does. This
+    /// is the same pick as [`SelectionStrategy::Spec`].
+    Firefox,
+    /// Safari and other WebKit browsers, which take the first density covering the
+    /// device pixel ratio in ascending order, or the highest. This is the same pick
+    /// as [`SelectionStrategy::Spec`].
+    WebKit,
+}
+
+/// The environment an image is selected for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectionContext {
+    /// The viewport width, in CSS pixels.
+    pub viewport_width: f64,
+    /// The number of device pixels per CSS pixel.
+    pub device_pixel_ratio: f64,
+    /// The width the image is rendered at, in CSS pixels, as given by the evaluated
+    /// `sizes` attribute. `None` means the default of `100vw`.
+    pub source_size: Option<f64>,
+    /// The browser behavior to model.
+    pub strategy: SelectionStrategy,
+}
+
+impl SelectionContext {
+    pub fn new(viewport_width: f64, device_pixel_ratio: f64) -> Self {
+        Self {
+            viewport_width,
+            device_pixel_ratio,
+            source_size: None,
+            strategy: SelectionStrategy::Spec,
+        }
+    }
+
+    pub fn with_source_size(mut self, source_size: f64) -> Self {
+        self.source_size = Some(source_size);
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: SelectionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// The source size in CSS pixels, falling back to the viewport width.
+    pub fn effective_source_size(&self) -> f64 {
+        self.source_size.unwrap_or(self.viewport_width)
+    }
+}
+
+impl ImageCandidate {
+    /// The pixel density of the candidate when rendered `source_size` CSS pixels wide.
+    ///
+    /// A `w` descriptor is divided by the source size, and a candidate without
+    /// descriptors counts as `1x`. Returns `None` if the candidate has both a width
+    /// and a density, or if the density isn't a positive number.
+    ///
+    /// # Examples
+    /// ```
+    /// let candidates = srcset_parse::parse("a.png 800w, b.png 2x, c.png");
+    /// let densities: Vec<_> = candidates.iter().map(|c| c.effective_density(400.0)).collect();
+    /// assert_eq!(densities, vec![Some(2.0), Some(2.0), Some(1.0)]);
+    /// ```
+    pub fn effective_density(&self, source_size: f64) -> Option<f64> {
+        let density = match (self.width, self.density) {
+            (Some(_), Some(_)) => return None,
+            (Some(width), None) => width / source_size,
+            (None, Some(density)) => density,
+            (None, None) => 1.0,
+        };
+        (density.is_finite() && density > 0.0).then_some(density)
+    }
+}
+
+/// Selects the candidate a browser would fetch in `context`.
+///
+/// Every candidate is normalized to a pixel density, candidates whose density equals
+/// an earlier one are dropped, and one is picked according to the context's
+/// [`SelectionStrategy`]. By default, that is the one with the lowest density that
+/// still covers the device pixel ratio, or the one with the highest density if none
+/// covers it. Returns `None` if there are no usable candidates.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::{select, SelectionContext, SelectionStrategy};
+///
+/// let candidates = srcset_parse::parse("small.jpg 400w, medium.jpg 800w, large.jpg 1200w");
+/// let context = SelectionContext::new(375.0, 2.0);
+/// assert_eq!(select(&candidates, &context).unwrap().url, "medium.jpg");
+///
+/// let candidates = srcset_parse::parse("a.jpg 1.5x, b.jpg 3x");
+/// assert_eq!(select(&candidates, &context).unwrap().url, "b.jpg");
+/// let context = context.with_strategy(SelectionStrategy::Chromium);
+/// assert_eq!(select(&candidates, &context).unwrap().url, "a.jpg");
+/// ```
+pub fn select<'a>(
+    candidates: &'a [ImageCandidate],
+    context: &SelectionContext,
+) -> Option<&'a ImageCandidate> {
+    run(candidates, context, |_, _, _| {}).map(|(i, _)| &candidates[i])
+}
+
+/// Selects a candidate like [`select`], also returning a [`SelectionTrace`] that
+/// records each candidate's effective density, the duplicates that were dropped
+/// and the comparison that decided the selection.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::{select_with_trace, CandidateOutcome, Decision, SelectionContext};
+///
+/// let candidates = srcset_parse::parse("a.jpg 400w, b.jpg 800w, c.jpg 2x");
+/// let (selected, trace) = select_with_trace(&candidates, &SelectionContext::new(400.0, 1.5));
+/// assert_eq!(selected.unwrap().url, "b.jpg");
+/// assert_eq!(trace.candidates[2].outcome, CandidateOutcome::Duplicate { of: 1 });
+/// assert_eq!(trace.decision, Some(Decision::LowestCovering));
+/// assert!(trace.to_string().ends_with("selected b.jpg: 2x is the lowest density covering 1.5x"));
+/// ```
+pub fn select_with_trace<'a>(
+    candidates: &'a [ImageCandidate],
+    context: &SelectionContext,
+) -> (Option<&'a ImageCandidate>, SelectionTrace) {
+    let source_size = context.effective_source_size();
+    let mut traces: Vec<CandidateTrace> = Vec::with_capacity(candidates.len());
+    let picked = run(candidates, context, |i, density, outcome| {
+        traces.push(CandidateTrace {
+            candidate: candidates[i].clone(),
+            density,
+            outcome,
+        });
+    });
+    if let Some((i, _)) = picked {
+        traces[i].outcome = CandidateOutcome::Selected;
+    }
+
+    let trace = SelectionTrace {
+        source_media: None,
+        source_size,
+        source_size_origin: match context.source_size {
+            Some(_) => SourceSizeOrigin::Context,
+            None => SourceSizeOrigin::Viewport,
+        },
+        device_pixel_ratio: context.device_pixel_ratio,
+        strategy: context.strategy,
+        candidates: traces,
+        decision: picked.map(|(_, decision)| decision),
+    };
+    (picked.map(|(i, _)| &candidates[i]), trace)
+}
+
+/// The selection shared by [`select`] and [`select_with_trace`]: normalizes every
+/// candidate to a density, drops duplicates and picks one, returning its index
+/// and why it won. `visit` is called with each candidate's index, density and
+/// outcome, in order, before the pick.
+fn run(
+    candidates: &[ImageCandidate],
+    context: &SelectionContext,
+    mut visit: impl FnMut(usize, Option<f64>, CandidateOutcome),
+) -> Option<(usize, Decision)> {
+    let source_size = context.effective_source_size();
+    let mut densities: Vec<(f64, usize)> = Vec::with_capacity(candidates.len());
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        let density = candidate.effective_density(source_size);
+        let outcome = match density {
+            None => CandidateOutcome::Unusable,
+            Some(density) => match densities.iter().find(|&&(d, _)| d == density) {
+                Some(&(_, of)) => CandidateOutcome::Duplicate { of },
+                None => {
+                    densities.push((density, i));
+                    CandidateOutcome::Considered
+                }
+            },
+        };
+        visit(i, density, outcome);
+    }
+
+    pick(&mut densities, context)
+}
+
+/// Picks among distinct `(density, index)` pairs according to the context's
+/// strategy, returning the index of the winner and why it won.
+fn pick(densities: &mut [(f64, usize)], context: &SelectionContext) -> Option<(usize, Decision)> {
+    let dpr = context.device_pixel_ratio;
+    match context.strategy {
+        SelectionStrategy::Spec | SelectionStrategy::Firefox | SelectionStrategy::WebKit => {
+            let covering = densities
+                .iter()
+                .filter(|&&(d, _)| d >= dpr)
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|&(_, i)| (i, Decision::LowestCovering));
+            covering.or_else(|| {
+                let highest = densities.iter().max_by(|a, b| a.0.total_cmp(&b.0));
+                highest.map(|&(_, i)| (i, Decision::Highest))
+            })
+        }
+        SelectionStrategy::Chromium => {
+            densities.sort_by(|a, b| a.0.total_cmp(&b.0));
+            // Like Blink, find the first density followed by one covering the
+            // ratio, then choose between the two. Densities are positive, so
+            // comparing the squared ratio with the product is comparing the ratio
+            // with the geometric mean.
+            let Some(pair) = densities.windows(2).find(|pair| pair[1].0 >= dpr) else {
+                return densities.last().map(|&(_, i)| (i, Decision::Highest));
+            };
+            let ((density, i), (next, j)) = (pair[0], pair[1]);
+            Some(if dpr <= 1.0 && dpr > density {
+                (j, Decision::LowRatio { previous: density })
+            } else if dpr * dpr >= density * next {
+                (j, Decision::GeometricMeanReached { previous: density })
+            } else if density >= dpr {
+                (i, Decision::LowestCovering)
+            } else {
+                (i, Decision::GeometricMean { next })
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{select, select_with_trace, SelectionContext, SelectionStrategy};
+    use crate::trace::{CandidateOutcome, Decision, SourceSizeOrigin};
+    use crate::{parse, ImageCandidate};
+
+    fn selected(srcset: &str, context: SelectionContext) -> Option<String> {
+        select(&parse(srcset), &context).map(|c| c.url.clone())
+    }
+
+    #[test]
+    fn picks_the_smallest_density_covering_the_dpr() {
+        let srcset = "a.png 1x, b.png 2x, c.png 3x";
+        assert_eq!(
+            selected(srcset, SelectionContext::new(400.0, 1.0)).as_deref(),
+            Some("a.png")
+        );
+        assert_eq!(
+            selected(srcset, SelectionContext::new(400.0, 1.5)).as_deref(),
+            Some("b.png")
+        );
+        assert_eq!(
+            selected(srcset, SelectionContext::new(400.0, 4.0)).as_deref(),
+            Some("c.png")
+        );
+    }
+
+    #[test]
+    fn normalizes_widths_with_the_source_size() {
+        let srcset = "a.png 320w, b.png 640w, c.png 1280w";
+        let context = SelectionContext::new(1000.0, 1.0);
+        assert_eq!(selected(srcset, context).as_deref(), Some("c.png"));
+        assert_eq!(
+            selected(srcset, context.with_source_size(320.0)).as_deref(),
+            Some("a.png")
+        );
+        assert_eq!(
+            selected(srcset, SelectionContext::new(320.0, 2.0)).as_deref(),
+            Some("b.png")
+        );
+    }
+
+    #[test]
+    fn treats_missing_descriptors_as_1x_and_drops_duplicates() {
+        let srcset = "a.png, b.png 1x, c.png 2x";
+        assert_eq!(
+            selected(srcset, SelectionContext::new(400.0, 1.0)).as_deref(),
+            Some("a.png")
+        );
+        assert_eq!(selected("", SelectionContext::new(400.0, 1.0)), None);
+    }
+
+    #[test]
+    fn models_chromium_with_geometric_means() {
+        let chromium =
+            |dpr| SelectionContext::new(400.0, dpr).with_strategy(SelectionStrategy::Chromium);
+        let srcset = "c.png 3x, a.png 1x, b.png 1.5x";
+        // sqrt(1.5 * 3) is about 2.12.
+        assert_eq!(selected(srcset, chromium(2.0)).as_deref(), Some("b.png"));
+        assert_eq!(selected(srcset, chromium(2.2)).as_deref(), Some("c.png"));
+        assert_eq!(selected(srcset, chromium(5.0)).as_deref(), Some("c.png"));
+        // At 1x and below, a.png covers the ratio.
+        assert_eq!(selected(srcset, chromium(1.0)).as_deref(), Some("a.png"));
+        assert_eq!(selected(srcset, chromium(0.5)).as_deref(), Some("a.png"));
+        // At 1x or less, a density below the ratio never wins.
+        assert_eq!(
+            selected("a 0.5x, b 2x", chromium(1.0)).as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            selected("a 0.9x, b 1.5x", chromium(1.0)).as_deref(),
+            Some("b")
+        );
+        // Above 1x, sqrt(0.9 * 2), about 1.34, decides as usual.
+        assert_eq!(
+            selected("a 0.9x, b 2x", chromium(1.2)).as_deref(),
+            Some("a")
+        );
+        // sqrt(1 * 4) is exactly 2, and reaching the mean picks the denser one.
+        assert_eq!(selected("a 1x, b 4x", chromium(2.0)).as_deref(), Some("b"));
+        assert_eq!(selected("a 1x, b 4x", chromium(1.9)).as_deref(), Some("a"));
+        assert_eq!(selected("", chromium(1.0)), None);
+
+        for strategy in [SelectionStrategy::Firefox, SelectionStrategy::WebKit] {
+            let context = SelectionContext::new(400.0, 2.0).with_strategy(strategy);
+            assert_eq!(selected(srcset, context).as_deref(), Some("c.png"));
+        }
+    }
+
+    #[test]
+    fn traces_every_step() {
+        let mut candidates = parse("b.png 200w, c.png 1x, d.png 400w");
+        candidates.insert(
+            0,
+            ImageCandidate {
+                url: "a.png".to_string(),
+                width: Some(100.0),
+                density: Some(2.0),
+                height: None,
+            },
+        );
+        let context = SelectionContext::new(1000.0, 2.0).with_source_size(200.0);
+        let (selected, trace) = select_with_trace(&candidates, &context);
+        assert_eq!(selected, select(&candidates, &context));
+
+        let outcomes: Vec<_> = trace.candidates.iter().map(|c| c.outcome).collect();
+        assert_eq!(
+            outcomes,
+            [
+                CandidateOutcome::Unusable,
+                CandidateOutcome::Considered,
+                CandidateOutcome::Duplicate { of: 1 },
+                CandidateOutcome::Selected,
+            ]
+        );
+        assert_eq!(trace.candidates[3].density, Some(2.0));
+        assert_eq!(trace.source_size_origin, SourceSizeOrigin::Context);
+        assert_eq!(trace.decision, Some(Decision::LowestCovering));
+        assert_eq!(
+            trace.to_string(),
+            "source size: 200px, as given\n\
+             device pixel ratio: 2 (Spec strategy)\n\
+             candidates:\n  \
+             1. a.png 100w 2x: no density, ignored\n  \
+             2. b.png 200w: 1x\n  \
+             3. c.png 1x: 1x, duplicate of 2\n  \
+             4. d.png 400w: 2x, selected\n\
+             selected d.png: 2x is the lowest density covering 2x"
+        );
+
+        let context = SelectionContext::new(300.0, 2.0).with_strategy(SelectionStrategy::Chromium);
+        let (_, trace) = select_with_trace(&parse("a.png 1.5x, b.png 3x"), &context);
+        assert_eq!(trace.decision, Some(Decision::GeometricMean { next: 3.0 }));
+        assert!(trace.to_string().ends_with(
+            "selected a.png: 1.5x is below 2x, but 2x is below its geometric mean with 3x"
+        ));
+
+        let (_, trace) = select_with_trace(&parse("a.png 1x, b.png 4x"), &context);
+        assert_eq!(
+            trace.decision,
+            Some(Decision::GeometricMeanReached { previous: 1.0 })
+        );
+        assert!(trace.to_string().ends_with(
+            "selected b.png: 4x is the lowest density covering 2x, and 2x reaches its \
+             geometric mean with 1x"
+        ));
+
+        let (selected, trace) = select_with_trace(&[], &context);
+        assert_eq!((selected, trace.decision), (None, None));
+        assert!(trace.to_string().ends_with("no candidate can be selected"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_traces() {
+        let context = SelectionContext::new(400.0, 1.0);
+        let (_, trace) = select_with_trace(&parse("a.png 1x, b.png 1x"), &context);
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["source_size_origin"], "Viewport");
+        assert_eq!(json["candidates"][1]["outcome"]["Duplicate"]["of"], 0);
+        assert_eq!(json["decision"], "LowestCovering");
+        assert_eq!(
+            serde_json::from_value::<super::SelectionTrace>(json).unwrap(),
+            trace
+        );
+    }
+}
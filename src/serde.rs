@@ -0,0 +1,131 @@
+//! Helpers for [`serde`](https://serde.rs), available with the `serde` feature.
+
+/// Serializes a candidate list as a `srcset` string, for use with
+/// `#[serde(with = "srcset_parse::serde::as_string")]`.
+///
+/// Serializing fails if a candidate can't be written as a valid `srcset`, and
+/// deserializing fails if the string has any problem [`crate::try_parse`] reports.
+/// The field can be a `Vec<ImageCandidate>` or a [`crate::Srcset`].
+///
+/// # Examples
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use srcset_parse::ImageCandidate;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Image {
+///     #[serde(with = "srcset_parse::serde::as_string")]
+///     srcset: Vec<ImageCandidate>,
+/// }
+///
+/// let image: Image = serde_json::from_str(r#"{"srcset":"cat.jpg 1x, cat@2x.jpg 2x"}"#).unwrap();
+/// assert_eq!(image.srcset[1].density, Some(2.0));
+/// assert_eq!(
+///     serde_json::to_string(&image).unwrap(),
+///     r#"{"srcset":"cat.jpg 1x, cat@2x.jpg 2x"}"#
+/// );
+/// ```
+pub mod as_string {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use serde::de::{Deserialize, Deserializer, Error as _};
+    use serde::ser::{Error as _, Serializer};
+
+    use crate::{to_srcset_string, try_parse, ImageCandidate};
+
+    pub fn serialize<S: Serializer>(
+        candidates: &[ImageCandidate],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let srcset = to_srcset_string(candidates).map_err(S::Error::custom)?;
+        serializer.serialize_str(&srcset)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<Vec<ImageCandidate>>,
+    {
+        let srcset = String::deserialize(deserializer)?;
+        let parsed = try_parse(&srcset).map_err(D::Error::custom)?;
+        Ok(T::from(parsed.into_candidates()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::num::NonZeroU32;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        parse, parse_with_diagnostics, try_parse, Descriptor, Diagnostic, ImageCandidate,
+        ParseError, Srcset,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Manifest {
+        #[serde(with = "crate::serde::as_string")]
+        srcset: Srcset,
+    }
+
+    #[test]
+    fn derives_for_public_types() {
+        let candidates = parse("a.png 100w 50h");
+        let json = serde_json::to_string(&candidates[0]).unwrap();
+        assert_eq!(
+            json,
+            r#"{"url":"a.png","width":100.0,"density":null,"height":50.0}"#
+        );
+        let back: ImageCandidate = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, candidates[0]);
+
+        let srcset = Srcset::new(candidates);
+        assert_eq!(serde_json::to_string(&srcset).unwrap(), format!("[{json}]"));
+
+        let (_, diagnostics) = parse_with_diagnostics("a.png 2x 3x");
+        let json = serde_json::to_string(&diagnostics).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Diagnostic>>(&json).unwrap(),
+            diagnostics
+        );
+
+        let (width, height) = (NonZeroU32::new(100).unwrap(), NonZeroU32::new(50).unwrap());
+        let json = serde_json::to_string(&Descriptor::WidthHeight(width, height)).unwrap();
+        assert_eq!(json, r#"{"WidthHeight":[100,50]}"#);
+    }
+
+    #[test]
+    fn rejects_parse_errors_without_diagnostics() {
+        let error = try_parse("a.png 2x 3x").unwrap_err();
+        let json = serde_json::to_string(&error).unwrap();
+        assert_eq!(serde_json::from_str::<ParseError>(&json).unwrap(), error);
+
+        assert!(serde_json::from_str::<ParseError>(r#"{"diagnostics":[]}"#).is_err());
+    }
+
+    #[test]
+    fn round_trips_srcsets_as_strings() {
+        let manifest: Manifest = serde_json::from_str(r#"{"srcset":"a.png 1x,b.png 2x"}"#).unwrap();
+        assert_eq!(manifest.srcset.len(), 2);
+        assert_eq!(
+            serde_json::to_string(&manifest).unwrap(),
+            r#"{"srcset":"a.png 1x, b.png 2x"}"#
+        );
+
+        let error = serde_json::from_str::<Manifest>(r#"{"srcset":"a.png 1.5w"}"#).unwrap_err();
+        assert!(error.to_string().contains("invalid srcset"));
+
+        let invalid = Manifest {
+            srcset: Srcset::new(vec![ImageCandidate {
+                url: String::new(),
+                width: None,
+                density: None,
+                height: None,
+            }]),
+        };
+        assert!(serde_json::to_string(&invalid).is_err());
+    }
+}
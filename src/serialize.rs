@@ -0,0 +1,223 @@
+//! Writing image candidates back out as `srcset` strings.
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use crate::{ImageCandidate, SerializeError, Srcset};
+
+/// Writes `url` so that it is read back unchanged: whitespace would end the URL and
+/// leading or trailing commas would be taken as separators, so they are
+/// percent-encoded.
+pub(crate) fn write_url(f: &mut impl Write, url: &str) -> fmt::Result {
+    let rest = url.trim_start_matches(',');
+    let middle = rest.trim_end_matches(',');
+
+    for _ in 0..url.len() - rest.len() {
+        f.write_str("%2C")?;
+    }
+    for c in middle.chars() {
+        if c.is_whitespace() {
+            for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                write!(f, "%{b:02X}")?;
+            }
+        } else {
+            f.write_char(c)?;
+        }
+    }
+    for _ in 0..rest.len() - middle.len() {
+        f.write_str("%2C")?;
+    }
+
+    Ok(())
+}
+
+/// Normalizes `-0` to `0`, so that a descriptor value doesn't display as `-0`.
+/// `f64`'s `Display` already writes whole numbers without a fraction, e.g. `2`.
+pub(crate) fn number(value: f64) -> f64 {
+    if value == 0.0 {
+        0.0
+    } else {
+        value
+    }
+}
+
+fn is_positive_integer(value: f64) -> bool {
+    value.is_finite() && value > 0.0 && value % 1.0 == 0.0
+}
+
+impl fmt::Display for ImageCandidate {
+    /// Writes the candidate in `srcset` syntax, e.g. `cat.jpg 400w 300h`.
+    ///
+    /// This never fails; use [`to_srcset_string`] to also check that the
+    /// descriptors are valid.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_url(f, &self.url)?;
+        if let Some(width) = self.width {
+            write!(f, " {}w", number(width))?;
+        }
+        if let Some(height) = self.height {
+            write!(f, " {}h", number(height))?;
+        }
+        if let Some(density) = self.density {
+            write!(f, " {}x", number(density))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Srcset {
+    /// Writes the candidates separated by `, `. See [`ImageCandidate`]'s `Display`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, candidate) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{candidate}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ImageCandidate {
+    /// Checks that the candidate can be written as valid `srcset` syntax.
+    fn validate(&self, index: usize) -> Result<(), SerializeError> {
+        if self.url.is_empty() {
+            return Err(SerializeError::EmptyUrl { index });
+        }
+        if self.width.is_some() && self.density.is_some() {
+            return Err(SerializeError::ConflictingDescriptors { index });
+        }
+        if self.width.is_some_and(|w| !is_positive_integer(w)) {
+            return Err(SerializeError::InvalidWidth { index });
+        }
+        if self.density.is_some_and(|d| !d.is_finite() || d < 0.0) {
+            return Err(SerializeError::InvalidDensity { index });
+        }
+        if self
+            .height
+            .is_some_and(|h| !is_positive_integer(h) || self.width.is_none())
+        {
+            return Err(SerializeError::InvalidHeight { index });
+        }
+        Ok(())
+    }
+}
+
+/// Serializes candidates into a canonical `srcset` string, which both [`crate::parse`]
+/// and [`crate::parse_spec`] read back into the same candidates.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::{to_srcset_string, ImageCandidate};
+///
+/// let candidates = vec![
+///     ImageCandidate { url: "cat 1.png".into(), width: None, density: Some(2.0), height: None },
+///     ImageCandidate { url: "dog.png".into(), width: Some(400.0), density: None, height: None },
+/// ];
+/// assert_eq!(to_srcset_string(&candidates).unwrap(), "cat%201.png 2x, dog.png 400w");
+/// ```
+pub fn to_srcset_string(candidates: &[ImageCandidate]) -> Result<String, SerializeError> {
+    let mut result = String::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        candidate.validate(index)?;
+        if index > 0 {
+            result.push_str(", ");
+        }
+        write!(result, "{candidate}").expect("writing to a String never fails");
+    }
+    Ok(result)
+}
+
+impl Srcset {
+    /// Serializes the candidates into a canonical `srcset` string. See
+    /// [`to_srcset_string`].
+    pub fn to_srcset_string(&self) -> Result<String, SerializeError> {
+        to_srcset_string(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::to_srcset_string;
+    use crate::tests::candidate;
+    use crate::{parse, parse_spec, ImageCandidate, SerializeError};
+
+    #[test]
+    fn formats_descriptors_canonically() {
+        let candidates = vec![
+            candidate("a.png", None, Some(2.0)),
+            candidate("b.png", None, Some(1.5)),
+            candidate("c.png", None, Some(-0.0)),
+            ImageCandidate {
+                height: Some(300.0),
+                ..candidate("d.png", Some(400.0), None)
+            },
+            candidate("e.png", None, None),
+        ];
+        assert_eq!(
+            to_srcset_string(&candidates).unwrap(),
+            "a.png 2x, b.png 1.5x, c.png 0x, d.png 400w 300h, e.png"
+        );
+    }
+
+    #[test]
+    fn escapes_urls_that_would_not_round_trip() {
+        let candidates = vec![
+            candidate(",a b\tc,", None, Some(1.0)),
+            candidate("d\u{a0}e.png", Some(10.0), None),
+        ];
+        let serialized = to_srcset_string(&candidates).unwrap();
+        assert_eq!(serialized, "%2Ca%20b%09c%2C 1x, d%C2%A0e.png 10w");
+
+        let urls = ["%2Ca%20b%09c%2C", "d%C2%A0e.png"];
+        for parsed in [parse(&serialized), parse_spec(&serialized)] {
+            assert_eq!(parsed.iter().map(|c| &c.url).collect::<Vec<_>>(), urls);
+        }
+    }
+
+    #[test]
+    fn round_trips_through_the_parsers() {
+        let candidates = vec![
+            candidate("https://a.b/w=1,h=2/c.png", Some(1024.0), None),
+            candidate("data:image/png;base64,AAAA", None, Some(0.5)),
+        ];
+        let serialized = to_srcset_string(&candidates).unwrap();
+        assert_eq!(parse(&serialized), candidates);
+        assert_eq!(parse_spec(&serialized), candidates);
+    }
+
+    #[test]
+    fn rejects_invalid_candidates() {
+        let cases = [
+            (
+                candidate("", None, None),
+                SerializeError::EmptyUrl { index: 1 },
+            ),
+            (
+                candidate("a", Some(1.0), Some(1.0)),
+                SerializeError::ConflictingDescriptors { index: 1 },
+            ),
+            (
+                candidate("a", Some(1.5), None),
+                SerializeError::InvalidWidth { index: 1 },
+            ),
+            (
+                candidate("a", None, Some(f64::NAN)),
+                SerializeError::InvalidDensity { index: 1 },
+            ),
+            (
+                ImageCandidate {
+                    height: Some(10.0),
+                    ..candidate("a", None, Some(1.0))
+                },
+                SerializeError::InvalidHeight { index: 1 },
+            ),
+        ];
+
+        for (invalid, error) in cases {
+            let candidates = [candidate("ok.png", None, None), invalid];
+            assert_eq!(to_srcset_string(&candidates), Err(error));
+        }
+    }
+}
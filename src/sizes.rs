@@ -0,0 +1,384 @@
+//! Parsing of the `sizes` attribute, following the HTML standard's
+//! ["parse a sizes attribute"](https://html.spec.whatwg.org/multipage/images.html#parse-a-sizes-attribute)
+//! algorithm.
+//!
+//! The `sizes` attribute tells the browser how wide an image will be rendered, so
+//! that the `w` descriptors of a `srcset` can be turned into pixel densities:
+//!
+//! ```
+//! use srcset_parse::sizes::{parse_sizes, SourceSizeValue};
+//!
+//! let sizes = parse_sizes("(max-width: 600px) 100vw, 50vw");
+//! assert_eq!(sizes.entries.len(), 1);
+//! assert_eq!(sizes.entries[0].0.as_str(), "(max-width: 600px)");
+//! assert_eq!(sizes.default, SourceSizeValue::Length("50vw".to_string()));
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::length::{parse_length, Length};
+use crate::lex::{is_css_whitespace, is_ident_char, split_top_level_commas};
+use crate::media::{parse_media_condition, MediaEnvironment};
+use crate::trace::SourceSizeOrigin;
+
+/// The size used when `sizes` is missing or has no entry without a media condition.
+const DEFAULT_SIZE: &str = "100vw";
+
+/// A media condition guarding a source size, e.g. `(max-width: 600px)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct MediaCondition(String);
+
+impl MediaCondition {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for MediaCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The value of a source size: a CSS `<length>`, such as `50vw` or
+/// `calc(100vw - 2rem)`, or the `auto` keyword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SourceSizeValue {
+    /// The image's layout width, for lazy-loaded images.
+    Auto,
+    /// A length, as written in the attribute.
+    Length(String),
+}
+
+impl fmt::Display for SourceSizeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceSizeValue::Auto => f.write_str("auto"),
+            SourceSizeValue::Length(length) => f.write_str(length),
+        }
+    }
+}
+
+/// A parsed `sizes` attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceSizes {
+    /// Whether the list starts with the `auto` keyword.
+    pub auto: bool,
+    /// The entries guarded by a media condition, in order. The first one whose
+    /// condition matches gives the source size.
+    pub entries: Vec<(MediaCondition, SourceSizeValue)>,
+    /// The size used when no condition matches: the first entry without a media
+    /// condition, or `100vw`.
+    pub default: SourceSizeValue,
+}
+
+impl Default for SourceSizes {
+    fn default() -> Self {
+        Self {
+            auto: false,
+            entries: Vec::new(),
+            default: SourceSizeValue::Length(DEFAULT_SIZE.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SourceSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.auto {
+            f.write_str("auto, ")?;
+        }
+        for (condition, value) in &self.entries {
+            write!(f, "{condition} {value}, ")?;
+        }
+        write!(f, "{}", self.default)
+    }
+}
+
+impl SourceSizes {
+    /// Evaluates the source size in CSS pixels: the size of the first entry whose
+    /// media condition matches, or the default. A candidate's width divided by the
+    /// source size gives its effective density.
+    ///
+    /// # Examples
+    /// ```
+    /// use srcset_parse::media::MediaEnvironment;
+    /// use srcset_parse::sizes::parse_sizes;
+    ///
+    /// let sizes = parse_sizes("(max-width: 600px) calc(100vw - 2rem), min(50vw, 640px)");
+    /// assert_eq!(sizes.evaluate(&MediaEnvironment::new(400.0, 800.0, 2.0)), 368.0);
+    /// assert_eq!(sizes.evaluate(&MediaEnvironment::new(1920.0, 1080.0, 1.0)), 640.0);
+    /// ```
+    pub fn evaluate(&self, env: &MediaEnvironment) -> f64 {
+        self.evaluate_with_layout_width(env, None)
+    }
+
+    /// Evaluates the source size like [`SourceSizes::evaluate`], resolving a
+    /// leading `auto` to `layout_width`: the width, in CSS pixels, a lazy-loaded
+    /// image is laid out at.
+    ///
+    /// Pass `None` for an image that isn't lazy-loaded or isn't rendered; `auto`
+    /// is then ignored and the rest of the list applies, as it does in browsers.
+    ///
+    /// # Examples
+    /// ```
+    /// use srcset_parse::media::MediaEnvironment;
+    /// use srcset_parse::sizes::parse_sizes;
+    ///
+    /// let sizes = parse_sizes("auto, (max-width: 600px) 100vw, 50vw");
+    /// let env = MediaEnvironment::new(1024.0, 768.0, 1.0);
+    /// assert_eq!(sizes.evaluate_with_layout_width(&env, Some(300.0)), 300.0);
+    /// assert_eq!(sizes.evaluate_with_layout_width(&env, None), 512.0);
+    /// ```
+    pub fn evaluate_with_layout_width(
+        &self,
+        env: &MediaEnvironment,
+        layout_width: Option<f64>,
+    ) -> f64 {
+        self.resolve(env, layout_width).0
+    }
+
+    /// Evaluates the source size like [`SourceSizes::evaluate_with_layout_width`],
+    /// also returning where it came from.
+    pub(crate) fn resolve(
+        &self,
+        env: &MediaEnvironment,
+        layout_width: Option<f64>,
+    ) -> (f64, SourceSizeOrigin) {
+        // `auto` is only honored as the first entry, so it wins when it applies.
+        if let Some(layout_width) = layout_width.filter(|_| self.auto) {
+            return (layout_width.max(0.0), SourceSizeOrigin::Auto);
+        }
+
+        let evaluate = |value: &SourceSizeValue| match value {
+            SourceSizeValue::Auto => None,
+            // A math function may evaluate to a negative size, which is clamped.
+            SourceSizeValue::Length(length) => {
+                parse_length(length).ok().map(|l| l.evaluate(env).max(0.0))
+            }
+        };
+
+        self.entries
+            .iter()
+            .filter(|(condition, _)| {
+                parse_media_condition(condition.as_str()).is_some_and(|c| c.matches(env))
+            })
+            .find_map(|(condition, value)| {
+                let origin = SourceSizeOrigin::Condition(condition.to_string());
+                Some((evaluate(value)?, origin))
+            })
+            .or_else(|| Some((evaluate(&self.default)?, SourceSizeOrigin::Default)))
+            .unwrap_or((env.viewport_width, SourceSizeOrigin::Viewport))
+    }
+}
+
+/// Splits the last component value off `input`, which must not end in whitespace.
+/// Returns the rest of the input and the component value.
+fn split_last_component(input: &str) -> (&str, &str) {
+    let start = if input.ends_with(')') {
+        // A function or a block: find the matching opening parenthesis, then
+        // include the function name, if any.
+        let mut depth = 0usize;
+        let mut open = 0;
+        for (i, c) in input.char_indices().rev() {
+            match c {
+                ')' => depth += 1,
+                '(' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        open = i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        input[..open].trim_end_matches(is_ident_char).len()
+    } else {
+        input
+            .trim_end_matches(|c: char| !is_css_whitespace(c) && c != ')')
+            .len()
+    };
+
+    input.split_at(start)
+}
+
+/// Parses a component value as a valid non-negative `<source-size-value>`.
+fn parse_source_size_value(component: &str) -> Option<SourceSizeValue> {
+    if component.eq_ignore_ascii_case("auto") {
+        return Some(SourceSizeValue::Auto);
+    }
+
+    match parse_length(component) {
+        // A source size can't be negative.
+        Ok(Length::Dimension { value, .. }) if value < 0.0 => None,
+        Ok(_) => Some(SourceSizeValue::Length(component.to_string())),
+        Err(_) => None,
+    }
+}
+
+/// Parses a `sizes` attribute.
+///
+/// Invalid entries are skipped, like browsers do. Entries after the first one
+/// without a media condition can never apply and are ignored.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::sizes::{parse_sizes, SourceSizeValue};
+///
+/// let sizes = parse_sizes("auto, (max-width: 600px) calc(100vw - 2rem), 640px");
+/// assert!(sizes.auto);
+/// assert_eq!(
+///     sizes.entries[0].1,
+///     SourceSizeValue::Length("calc(100vw - 2rem)".to_string())
+/// );
+/// assert_eq!(sizes.default, SourceSizeValue::Length("640px".to_string()));
+/// ```
+pub fn parse_sizes(sizes: &str) -> SourceSizes {
+    let mut result = SourceSizes::default();
+
+    for (i, unparsed) in split_top_level_commas(sizes).into_iter().enumerate() {
+        let unparsed = unparsed.trim_matches(is_css_whitespace);
+        if unparsed.is_empty() {
+            continue;
+        }
+
+        let (rest, component) = split_last_component(unparsed);
+        let Some(value) = parse_source_size_value(component) else {
+            continue;
+        };
+
+        let condition = rest.trim_end_matches(is_css_whitespace);
+        if condition.is_empty() {
+            match value {
+                // `auto` is only allowed as the first entry.
+                SourceSizeValue::Auto if i == 0 => result.auto = true,
+                SourceSizeValue::Auto => {}
+                value => {
+                    result.default = value;
+                    break;
+                }
+            }
+        } else if parse_media_condition(condition).is_some() {
+            result
+                .entries
+                .push((MediaCondition(condition.to_string()), value));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse_sizes, MediaCondition, SourceSizeValue, SourceSizes};
+    use crate::media::MediaEnvironment;
+
+    fn length(s: &str) -> SourceSizeValue {
+        SourceSizeValue::Length(s.to_string())
+    }
+
+    fn condition(s: &str) -> MediaCondition {
+        MediaCondition(s.to_string())
+    }
+
+    #[test]
+    fn parses_conditions_and_default() {
+        let sizes = parse_sizes(
+            " (max-width: 600px) 100vw,\n (min-width: 601px) and (max-width: 900px)50vw , 33.3vw",
+        );
+        assert_eq!(
+            sizes,
+            SourceSizes {
+                auto: false,
+                entries: vec![
+                    (condition("(max-width: 600px)"), length("100vw")),
+                    (
+                        condition("(min-width: 601px) and (max-width: 900px)"),
+                        length("50vw")
+                    ),
+                ],
+                default: length("33.3vw"),
+            }
+        );
+        assert_eq!(
+            sizes.to_string(),
+            "(max-width: 600px) 100vw, (min-width: 601px) and (max-width: 900px) 50vw, 33.3vw"
+        );
+    }
+
+    #[test]
+    fn defaults_to_full_viewport_width() {
+        assert_eq!(parse_sizes(""), SourceSizes::default());
+        assert_eq!(
+            parse_sizes("(max-width: 600px) 100vw").default,
+            length("100vw")
+        );
+    }
+
+    #[test]
+    fn supports_auto() {
+        let sizes = parse_sizes("AUTO, (max-width: 600px) auto, 50vw");
+        assert!(sizes.auto);
+        assert_eq!(
+            sizes.entries,
+            vec![(condition("(max-width: 600px)"), SourceSizeValue::Auto)]
+        );
+        assert_eq!(sizes.default, length("50vw"));
+
+        // `auto` anywhere else without a condition is ignored.
+        let sizes = parse_sizes("(max-width: 600px) 100vw, auto, 50vw");
+        assert!(!sizes.auto);
+        assert_eq!(sizes.default, length("50vw"));
+    }
+
+    #[test]
+    fn resolves_auto_to_the_layout_width() {
+        let env = MediaEnvironment::new(500.0, 800.0, 1.0);
+        let sizes = parse_sizes("auto, (max-width: 600px) auto, 50vw");
+        assert_eq!(sizes.evaluate_with_layout_width(&env, Some(120.0)), 120.0);
+        // Without a layout width, a conditional `auto` is skipped too.
+        assert_eq!(sizes.evaluate_with_layout_width(&env, None), 250.0);
+
+        // `auto` only applies when the list starts with it.
+        let sizes = parse_sizes("(max-width: 600px) auto, 50vw");
+        assert_eq!(sizes.evaluate_with_layout_width(&env, Some(120.0)), 250.0);
+        assert_eq!(parse_sizes("auto").evaluate(&env), 500.0);
+    }
+
+    #[test]
+    fn accepts_math_functions() {
+        let sizes = parse_sizes("(min-width: 1px) min(50vw, 640px), clamp(1px, 2vw, 3px)");
+        assert_eq!(sizes.entries[0].1, length("min(50vw, 640px)"));
+        assert_eq!(sizes.default, length("clamp(1px, 2vw, 3px)"));
+    }
+
+    #[test]
+    fn skips_invalid_entries() {
+        let sizes = parse_sizes(
+            "(max-width: 1px) 50%, (max-width: 2px) -1px, (max-width: 3px) 10, \
+             (max-width: 4px) var(--x), max-width: 5px 10px, 0",
+        );
+        assert_eq!(sizes.entries, vec![]);
+        assert_eq!(sizes.default, length("0"));
+
+        // An unclosed block runs to the end of the input, commas included.
+        assert_eq!(
+            parse_sizes("(max-width: 6px 10px, 20px"),
+            SourceSizes::default()
+        );
+    }
+
+    #[test]
+    fn stops_at_the_first_unconditional_size() {
+        let sizes = parse_sizes("10px, (max-width: 600px) 100vw, 20px");
+        assert_eq!(sizes.entries, vec![]);
+        assert_eq!(sizes.default, length("10px"));
+    }
+}
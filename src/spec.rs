@@ -0,0 +1,543 @@
+//! A step-by-step implementation of the HTML standard's
+//! ["parse a srcset attribute"](https://html.spec.whatwg.org/multipage/images.html#parse-a-srcset-attribute)
+//! algorithm.
+//!
+//! Unlike [`crate::parse`], which matches segments with a regular expression, this
+//! follows the spec's state machine exactly, so its output agrees with what browsers
+//! consider to be the candidates of a `srcset`.
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::lex::{is_digits, is_unsigned_number};
+use crate::{Diagnostic, ErrorKind, ImageCandidate, ParseError, Srcset};
+
+/// ASCII whitespace as defined by the Infra standard: TAB, LF, FF, CR and SPACE.
+fn is_ascii_whitespace(b: u8) -> bool {
+    matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+/// A slice of the input, along with the byte offset it starts at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Token<'a> {
+    pub text: &'a str,
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+}
+
+/// A URL and its unparsed descriptors, as produced by the splitting loop and the
+/// descriptor tokenizer.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RawCandidate<'a> {
+    pub url: Token<'a>,
+    pub descriptors: Vec<Token<'a>>,
+}
+
+/// Appends the current descriptor to the list, unless it is empty.
+fn push_descriptor<'a>(descriptors: &mut Vec<Token<'a>>, current: Token<'a>) {
+    if !current.text.is_empty() {
+        descriptors.push(current);
+    }
+}
+
+/// State of the descriptor tokenizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    InDescriptor,
+    InParens,
+    AfterDescriptor,
+}
+
+/// Runs the splitting loop of the algorithm, yielding one [`RawCandidate`] at a time.
+///
+/// All the characters the algorithm branches on are ASCII, so the tokenizer works on
+/// bytes and only ever slices the input at character boundaries.
+///
+/// Problems that don't belong to a single candidate's descriptors, such as stray
+/// commas, are collected in `diagnostics`.
+#[derive(Debug, Clone)]
+pub(crate) struct Tokenizer<'a> {
+    input: &'a str,
+    position: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            position: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    /// Collects a sequence of bytes matching `predicate`, returning the collected slice.
+    fn collect_while(&mut self, predicate: impl Fn(u8) -> bool) -> Token<'a> {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.position += 1;
+        }
+        self.since(start)
+    }
+
+    /// The input from `start` up to the current position, which must be at a
+    /// character boundary.
+    fn since(&self, start: usize) -> Token<'a> {
+        Token {
+            text: &self.input[start..self.position],
+            start,
+        }
+    }
+
+    /// The descriptor tokenizer. Consumes input up to and including the comma that
+    /// terminates the current candidate.
+    fn tokenize_descriptors(&mut self) -> Vec<Token<'a>> {
+        let mut descriptors = Vec::new();
+        self.collect_while(is_ascii_whitespace);
+
+        let mut state = State::InDescriptor;
+        let mut start = self.position;
+
+        loop {
+            let c = self.peek();
+
+            match state {
+                State::InDescriptor => match c {
+                    Some(b) if is_ascii_whitespace(b) => {
+                        push_descriptor(&mut descriptors, self.since(start));
+                        state = State::AfterDescriptor;
+                    }
+                    Some(b',') => {
+                        push_descriptor(&mut descriptors, self.since(start));
+                        self.position += 1;
+                        return descriptors;
+                    }
+                    Some(b'(') => state = State::InParens,
+                    None => {
+                        push_descriptor(&mut descriptors, self.since(start));
+                        return descriptors;
+                    }
+                    Some(_) => {}
+                },
+                State::InParens => match c {
+                    Some(b')') => state = State::InDescriptor,
+                    None => {
+                        descriptors.push(self.since(start));
+                        return descriptors;
+                    }
+                    Some(_) => {}
+                },
+                State::AfterDescriptor => match c {
+                    Some(b) if is_ascii_whitespace(b) => {}
+                    None => return descriptors,
+                    Some(_) => {
+                        // Reconsume the character in the "in descriptor" state.
+                        state = State::InDescriptor;
+                        start = self.position;
+                        continue;
+                    }
+                },
+            }
+
+            self.position += 1;
+        }
+    }
+
+    fn unexpected_commas(&mut self, span: Range<usize>) {
+        self.diagnostics.push(Diagnostic::new(
+            ErrorKind::UnexpectedComma,
+            span,
+            "unexpected comma between image candidates",
+        ));
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = RawCandidate<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip separators between candidates. Extra commas are a parse error, but do
+        // not affect the result.
+        let separators = self.collect_while(|b| is_ascii_whitespace(b) || b == b',');
+        if let (Some(first), Some(last)) = (separators.text.find(','), separators.text.rfind(',')) {
+            self.unexpected_commas(separators.start + first..separators.start + last + 1);
+        }
+        self.peek()?;
+
+        let url = self.collect_while(|b| !is_ascii_whitespace(b));
+
+        // A URL ending in a comma has no descriptors: the commas separate it from the
+        // next candidate. Only one of them is expected.
+        let trimmed = url.text.trim_end_matches(',');
+        let descriptors = if trimmed.len() < url.text.len() {
+            if url.text.len() - trimmed.len() > 1 {
+                self.unexpected_commas(url.start + trimmed.len() + 1..url.span().end);
+            }
+            Vec::new()
+        } else {
+            self.tokenize_descriptors()
+        };
+
+        Some(RawCandidate {
+            url: Token {
+                text: trimmed,
+                start: url.start,
+            },
+            descriptors,
+        })
+    }
+}
+
+/// Whether `s` is a "valid non-negative integer": one or more ASCII digits.
+fn is_valid_non_negative_integer(s: &str) -> bool {
+    is_digits(s)
+}
+
+/// Whether `s` is a "valid floating-point number", e.g. `1`, `-0.5`, `.5` or `1e1`.
+fn is_valid_floating_point_number(s: &str) -> bool {
+    is_unsigned_number(s.strip_prefix('-').unwrap_or(s))
+}
+
+/// Parses a validated number, rejecting values that overflow to infinity.
+fn parse_finite(s: &str) -> Option<f64> {
+    s.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// The result of the descriptor parser for a single candidate.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Descriptors {
+    width: Option<f64>,
+    density: Option<f64>,
+    height: Option<f64>,
+}
+
+/// Runs the descriptor parser. An error means the candidate has to be dropped.
+fn parse_descriptors(descriptors: &[Token<'_>]) -> Result<Descriptors, Diagnostic> {
+    let mut result = Descriptors::default();
+
+    for descriptor in descriptors {
+        let text = descriptor.text;
+        let error = |kind, message: &str| {
+            Err(Diagnostic::new(
+                kind,
+                descriptor.span(),
+                format!("{message} `{text}`"),
+            ))
+        };
+
+        // The tokenizer never produces empty descriptors.
+        let Some(kind) = text.chars().next_back() else {
+            continue;
+        };
+        let value = &text[..text.len() - kind.len_utf8()];
+
+        match kind {
+            'w' => {
+                if result.width.is_some() || result.density.is_some() {
+                    return error(ErrorKind::ConflictingDescriptors, "conflicting descriptor");
+                }
+                match parse_finite(value) {
+                    Some(w) if is_valid_non_negative_integer(value) && w != 0.0 => {
+                        result.width = Some(w)
+                    }
+                    _ => {
+                        return error(
+                            ErrorKind::InvalidWidth,
+                            "width must be a positive integer, found",
+                        )
+                    }
+                }
+            }
+            'x' => {
+                if result.width.is_some() || result.density.is_some() || result.height.is_some() {
+                    return error(ErrorKind::ConflictingDescriptors, "conflicting descriptor");
+                }
+                match parse_finite(value) {
+                    Some(d) if is_valid_floating_point_number(value) && d >= 0.0 => {
+                        result.density = Some(d)
+                    }
+                    _ => {
+                        return error(
+                            ErrorKind::InvalidDensity,
+                            "density must be a non-negative number, found",
+                        )
+                    }
+                }
+            }
+            'h' => {
+                if result.height.is_some() || result.density.is_some() {
+                    return error(ErrorKind::ConflictingDescriptors, "conflicting descriptor");
+                }
+                match parse_finite(value) {
+                    Some(h) if is_valid_non_negative_integer(value) && h != 0.0 => {
+                        result.height = Some(h)
+                    }
+                    _ => {
+                        return error(
+                            ErrorKind::InvalidHeight,
+                            "height must be a positive integer, found",
+                        )
+                    }
+                }
+            }
+            _ => return error(ErrorKind::UnknownDescriptor, "unknown descriptor"),
+        }
+    }
+
+    // The `h` descriptor is only allowed alongside a `w` descriptor.
+    if result.height.is_some() && result.width.is_none() {
+        let span = descriptors
+            .iter()
+            .find(|d| d.text.ends_with('h'))
+            .map_or(0..0, Token::span);
+        return Err(Diagnostic::new(
+            ErrorKind::HeightWithoutWidth,
+            span,
+            "height descriptor requires a width descriptor",
+        ));
+    }
+
+    Ok(result)
+}
+
+impl RawCandidate<'_> {
+    pub fn parse(&self) -> Result<ImageCandidate, Diagnostic> {
+        let descriptors = parse_descriptors(&self.descriptors)?;
+        Ok(ImageCandidate {
+            url: self.url.text.to_string(),
+            width: descriptors.width,
+            density: descriptors.density,
+            height: descriptors.height,
+        })
+    }
+}
+
+/// Parses an `srcset` string following the HTML standard's algorithm.
+///
+/// Candidates with invalid descriptors are dropped, exactly like browsers do.
+///
+/// # Examples
+/// ```
+/// let srcset = "data:image/png;base64,iVBORw0KGgo= 1x, image.png 1e1x, bad.png 1.x";
+/// let candidates = srcset_parse::parse_spec(srcset);
+/// assert_eq!(candidates.len(), 2);
+/// assert_eq!(candidates[0].url, "data:image/png;base64,iVBORw0KGgo=");
+/// assert_eq!(candidates[1].density, Some(10.0));
+/// ```
+pub fn parse_spec(srcset: &str) -> Vec<ImageCandidate> {
+    parse_spec_iter(srcset).collect()
+}
+
+/// Parses an `srcset` string like [`parse_spec`], running the tokenizer on demand
+/// as candidates are requested.
+///
+/// # Examples
+/// ```
+/// let mut candidates = srcset_parse::parse_spec_iter("a.png 1x, b.png 2x");
+/// assert_eq!(candidates.next().unwrap().url, "a.png");
+/// ```
+pub fn parse_spec_iter(srcset: &str) -> impl Iterator<Item = ImageCandidate> + '_ {
+    Tokenizer::new(srcset).filter_map(|raw| raw.parse().ok())
+}
+
+/// Parses an `srcset` string like [`parse_spec`], also returning every problem found
+/// in the input.
+///
+/// # Examples
+/// ```
+/// use srcset_parse::ErrorKind;
+///
+/// let (srcset, diagnostics) = srcset_parse::parse_with_diagnostics("a.png 2x, b.png 1.5w");
+/// assert_eq!(srcset.len(), 1);
+/// assert_eq!(diagnostics[0].kind, ErrorKind::InvalidWidth);
+/// assert_eq!(diagnostics[0].span, 16..20);
+/// ```
+pub fn parse_with_diagnostics(srcset: &str) -> (Srcset, Vec<Diagnostic>) {
+    let mut tokenizer = Tokenizer::new(srcset);
+    let mut candidates = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    while let Some(raw) = tokenizer.next() {
+        diagnostics.append(&mut tokenizer.diagnostics);
+        match raw.parse() {
+            Ok(candidate) => candidates.push(candidate),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    diagnostics.append(&mut tokenizer.diagnostics);
+
+    (Srcset::new(candidates), diagnostics)
+}
+
+/// Parses an `srcset` string following the HTML standard's algorithm, failing on any
+/// parse error instead of dropping the offending candidates.
+///
+/// # Examples
+/// ```
+/// let srcset = srcset_parse::try_parse("a.png 1x, b.png 2x").unwrap();
+/// assert_eq!(srcset.len(), 2);
+///
+/// let error = srcset_parse::try_parse("a.png 1x, b.png 2y").unwrap_err();
+/// assert_eq!(error.kind(), srcset_parse::ErrorKind::UnknownDescriptor);
+/// ```
+pub fn try_parse(srcset: &str) -> Result<Srcset, ParseError> {
+    let (srcset, diagnostics) = parse_with_diagnostics(srcset);
+    if diagnostics.is_empty() {
+        Ok(srcset)
+    } else {
+        Err(ParseError::new(diagnostics))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse_spec, parse_with_diagnostics, try_parse, Tokenizer};
+    use crate::{ErrorKind, ImageCandidate};
+
+    fn tokenize(input: &str) -> Vec<(&str, Vec<&str>)> {
+        Tokenizer::new(input)
+            .map(|raw| {
+                let descriptors = raw.descriptors.iter().map(|d| d.text).collect();
+                (raw.url.text, descriptors)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_descriptors_with_parentheses() {
+        assert_eq!(
+            tokenize("a.png 1x (foo, bar) 2x, b.png (unterminated"),
+            vec![
+                ("a.png", vec!["1x", "(foo, bar)", "2x"]),
+                ("b.png", vec!["(unterminated"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_trailing_commas_from_urls() {
+        assert_eq!(
+            tokenize(",, a.png,,, b.png 2x,"),
+            vec![("a.png", vec![]), ("b.png", vec!["2x"])]
+        );
+    }
+
+    #[test]
+    fn keeps_commas_inside_urls() {
+        let result = parse_spec("data:image/gif;base64,R0lGODlh 2x, https://a.b/w=1,h=2/c.png");
+        assert_eq!(
+            result,
+            vec![
+                ImageCandidate {
+                    url: "data:image/gif;base64,R0lGODlh".to_string(),
+                    width: None,
+                    density: Some(2.0),
+                    height: None,
+                },
+                ImageCandidate {
+                    url: "https://a.b/w=1,h=2/c.png".to_string(),
+                    width: None,
+                    density: None,
+                    height: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_splits_on_ascii_whitespace() {
+        let result = parse_spec("cat.png\u{a0}2x");
+        assert_eq!(
+            result,
+            vec![ImageCandidate {
+                url: "cat.png\u{a0}2x".to_string(),
+                width: None,
+                density: None,
+                height: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_floating_point_densities() {
+        let densities: Vec<_> = parse_spec("a 1e1x, b .5x, c 2.50x, d 1E-1x, e 0x")
+            .into_iter()
+            .map(|c| c.density)
+            .collect();
+        assert_eq!(
+            densities,
+            vec![Some(10.0), Some(0.5), Some(2.5), Some(0.1), Some(0.0)]
+        );
+    }
+
+    #[test]
+    fn drops_candidates_with_invalid_descriptors() {
+        let srcset =
+            "a 1.x, b -1x, c 0w, d 1.5w, e 100w 2x, f 1x 1x, g 10h, h 1y, i +1x, j 1é, k 100w 50h";
+        let urls: Vec<_> = parse_spec(srcset).into_iter().map(|c| c.url).collect();
+        assert_eq!(urls, vec!["k"]);
+    }
+
+    #[test]
+    fn reports_diagnostics_with_spans() {
+        let srcset = ", a.png 1x 2x, b.png,, c.png 10h";
+        let (result, diagnostics) = parse_with_diagnostics(srcset);
+        assert_eq!(result.len(), 1);
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.kind, &srcset[d.span.clone()]))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (ErrorKind::UnexpectedComma, ","),
+                (ErrorKind::ConflictingDescriptors, "2x"),
+                (ErrorKind::UnexpectedComma, ","),
+                (ErrorKind::HeightWithoutWidth, "10h"),
+            ]
+        );
+    }
+
+    #[test]
+    fn try_parse_fails_on_any_error() {
+        assert_eq!(try_parse("").unwrap().len(), 0);
+        assert_eq!(try_parse("a.png, b.png 2x,").unwrap().len(), 2);
+
+        let error = try_parse("a.png 1x,, b.png 0w").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedComma);
+        assert_eq!(error.diagnostics().len(), 2);
+        assert_eq!(
+            error.to_string(),
+            "invalid srcset: unexpected comma between image candidates (at 9..10) (and 1 more problem)"
+        );
+    }
+
+    #[test]
+    fn parses_heights_alongside_widths() {
+        let heights: Vec<_> = parse_spec("a 400w 300h, b 300h 400w, c 400w")
+            .into_iter()
+            .map(|c| (c.width, c.height))
+            .collect();
+        assert_eq!(
+            heights,
+            vec![
+                (Some(400.0), Some(300.0)),
+                (Some(400.0), Some(300.0)),
+                (Some(400.0), None),
+            ]
+        );
+    }
+}
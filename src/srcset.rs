@@ -0,0 +1,70 @@
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use crate::ImageCandidate;
+
+/// A parsed `srcset`: the list of its image candidates, in source order.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Srcset {
+    candidates: Vec<ImageCandidate>,
+}
+
+impl Srcset {
+    pub fn new(candidates: Vec<ImageCandidate>) -> Self {
+        Self { candidates }
+    }
+
+    pub fn candidates(&self) -> &[ImageCandidate] {
+        &self.candidates
+    }
+
+    pub fn into_candidates(self) -> Vec<ImageCandidate> {
+        self.candidates
+    }
+}
+
+impl Deref for Srcset {
+    type Target = [ImageCandidate];
+
+    fn deref(&self) -> &Self::Target {
+        &self.candidates
+    }
+}
+
+impl From<Vec<ImageCandidate>> for Srcset {
+    fn from(candidates: Vec<ImageCandidate>) -> Self {
+        Self::new(candidates)
+    }
+}
+
+impl From<Srcset> for Vec<ImageCandidate> {
+    fn from(srcset: Srcset) -> Self {
+        srcset.candidates
+    }
+}
+
+impl FromIterator<ImageCandidate> for Srcset {
+    fn from_iter<I: IntoIterator<Item = ImageCandidate>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Srcset {
+    type Item = ImageCandidate;
+    type IntoIter = alloc::vec::IntoIter<ImageCandidate>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.candidates.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Srcset {
+    type Item = &'a ImageCandidate;
+    type IntoIter = core::slice::Iter<'a, ImageCandidate>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.candidates.iter()
+    }
+}
@@ -0,0 +1,180 @@
+//! Explanations of how a candidate was selected.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{ImageCandidate, SelectionStrategy};
+
+/// Where the source size of a selection came from.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SourceSizeOrigin {
+    /// The `source_size` of the [`crate::SelectionContext`].
+    Context,
+    /// Nothing gave a source size, so the viewport width was used.
+    Viewport,
+    /// A `sizes` list starting with `auto`, resolved to the image's layout width.
+    Auto,
+    /// The `sizes` entry with this media condition, the first one that matched.
+    Condition(String),
+    /// The `sizes` entry without a media condition, as no condition matched.
+    Default,
+}
+
+/// What happened to a candidate during selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CandidateOutcome {
+    /// The candidate has no usable density, e.g. it has both a width and a
+    /// density, so it was ignored.
+    Unusable,
+    /// The candidate has the same density as the earlier candidate at index `of`,
+    /// so it was dropped.
+    Duplicate {
+        of: usize,
+    },
+    /// The candidate was considered but not selected.
+    Considered,
+    Selected,
+}
+
+/// A candidate, as seen by the selection.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CandidateTrace {
+    pub candidate: ImageCandidate,
+    /// The effective density of the candidate, if it has a usable one.
+    pub density: Option<f64>,
+    pub outcome: CandidateOutcome,
+}
+
+/// The comparison that decided which candidate was selected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Decision {
+    /// Its density is the lowest of those covering the device pixel ratio.
+    LowestCovering,
+    /// Its density is the highest, as no lower one qualified.
+    Highest,
+    /// Chromium: its density is below the device pixel ratio, but the ratio is
+    /// below the geometric mean of it and `next`, the lowest density covering
+    /// the ratio.
+    GeometricMean { next: f64 },
+    /// Chromium: its density is the lowest covering the device pixel ratio, and
+    /// the ratio reaches the geometric mean of it and `previous`, the density
+    /// just below.
+    GeometricMeanReached { previous: f64 },
+    /// Chromium, with a device pixel ratio of 1 or less: its density is the lowest
+    /// covering the ratio, and `previous`, the density just below, doesn't.
+    LowRatio { previous: f64 },
+}
+
+/// A record of every step of a selection, returned by
+/// [`crate::select_with_trace`] and [`crate::picture::Picture::select_with_trace`].
+///
+/// Its `Display` implementation explains the selection in plain text; with the
+/// `serde` feature, it can also be serialized as structured data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectionTrace {
+    /// The `media` of the `<source>` that was used, if it has one.
+    pub source_media: Option<String>,
+    /// The source size, in CSS pixels.
+    pub source_size: f64,
+    pub source_size_origin: SourceSizeOrigin,
+    pub device_pixel_ratio: f64,
+    pub strategy: SelectionStrategy,
+    /// Every candidate, in `srcset` order.
+    pub candidates: Vec<CandidateTrace>,
+    /// Why the selected candidate won, or `None` if no candidate was usable.
+    pub decision: Option<Decision>,
+}
+
+impl SelectionTrace {
+    /// The selected candidate, if any.
+    pub fn selected(&self) -> Option<&CandidateTrace> {
+        self.candidates
+            .iter()
+            .find(|c| c.outcome == CandidateOutcome::Selected)
+    }
+}
+
+/// Formats a density with at most three decimals, e.g. `2.133x`.
+fn density(value: f64) -> String {
+    let formatted = format!("{value:.3}");
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    format!("{formatted}x")
+}
+
+impl fmt::Display for SelectionTrace {
+    /// Explains the selection, one step per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(media) = &self.source_media {
+            writeln!(f, "source media matched: {media}")?;
+        }
+
+        write!(f, "source size: {}px, ", self.source_size)?;
+        match &self.source_size_origin {
+            SourceSizeOrigin::Context => writeln!(f, "as given")?,
+            SourceSizeOrigin::Viewport => writeln!(f, "the viewport width")?,
+            SourceSizeOrigin::Auto => writeln!(f, "the layout width, for sizes=auto")?,
+            SourceSizeOrigin::Condition(condition) => {
+                writeln!(f, "as sizes condition {condition} matched")?
+            }
+            SourceSizeOrigin::Default => writeln!(f, "the sizes default, as no condition matched")?,
+        }
+        writeln!(
+            f,
+            "device pixel ratio: {} ({:?} strategy)",
+            self.device_pixel_ratio, self.strategy
+        )?;
+
+        writeln!(f, "candidates:")?;
+        for (i, trace) in self.candidates.iter().enumerate() {
+            write!(f, "  {}. {}: ", i + 1, trace.candidate)?;
+            match trace.density {
+                Some(d) => f.write_str(&density(d))?,
+                None => f.write_str("no density")?,
+            }
+            match trace.outcome {
+                CandidateOutcome::Unusable => writeln!(f, ", ignored")?,
+                CandidateOutcome::Duplicate { of } => writeln!(f, ", duplicate of {}", of + 1)?,
+                CandidateOutcome::Considered => writeln!(f)?,
+                CandidateOutcome::Selected => writeln!(f, ", selected")?,
+            }
+        }
+
+        let (Some(selected), Some(decision)) = (self.selected(), self.decision) else {
+            return write!(f, "no candidate can be selected");
+        };
+        let selected_density = density(selected.density.unwrap_or_default());
+        let dpr = density(self.device_pixel_ratio);
+        write!(
+            f,
+            "selected {}: {selected_density} is ",
+            selected.candidate.url
+        )?;
+        match decision {
+            Decision::LowestCovering => write!(f, "the lowest density covering {dpr}"),
+            Decision::Highest => write!(f, "the highest density, as no lower one qualified"),
+            Decision::GeometricMean { next } => write!(
+                f,
+                "below {dpr}, but {dpr} is below its geometric mean with {}",
+                density(next)
+            ),
+            Decision::GeometricMeanReached { previous } => write!(
+                f,
+                "the lowest density covering {dpr}, and {dpr} reaches its geometric mean \
+                 with {}",
+                density(previous)
+            ),
+            Decision::LowRatio { previous } => write!(
+                f,
+                "the lowest density covering {dpr}, and {} doesn't at a ratio of 1 or less",
+                density(previous)
+            ),
+        }
+    }
+}